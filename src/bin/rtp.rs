@@ -0,0 +1,66 @@
+//! Estimates return-to-player for a ruleset.
+//!
+//! Usage: `rtp [ruleset.json] [games] [seed]`. Without a ruleset file the
+//! contract's catalogue and default rules are analyzed. Exits with status 1
+//! when the best reference strategy, replayed on a held-out seed, returns
+//! more than 100% with 95% confidence.
+
+use std::process::ExitCode;
+
+use statemachine_glitchbomb::rtp::{Ruleset, analyze};
+
+const DEFAULT_GAMES: u32 = 2_000;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let ruleset = match args.next().filter(|path| path != "-") {
+        Some(path) => {
+            let json = std::fs::read_to_string(&path).expect("failed to read ruleset");
+            serde_json::from_str(&json).expect("invalid ruleset json")
+        }
+        None => Ruleset::default(),
+    };
+    let games = args
+        .next()
+        .map(|games| games.parse().expect("games must be a number"))
+        .unwrap_or(DEFAULT_GAMES);
+    let seed = args
+        .next()
+        .map(|seed| seed.parse().expect("seed must be a number"))
+        .unwrap_or(0);
+
+    let report = analyze(&ruleset, games, seed);
+    println!("hp  level  5/die      rtp   mean diff  95% ci");
+    for (strategy, estimate) in &report.estimates {
+        println!(
+            "{:>2}  {:>5}  {:>5}  {:>6.1}%  {:>9.2}  [{:.2}, {:.2}]",
+            strategy.cash_out_hp,
+            strategy.target_level,
+            strategy.five_or_die,
+            estimate.rtp * 100.0,
+            estimate.mean_diff,
+            estimate.confidence_interval.0,
+            estimate.confidence_interval.1,
+        );
+    }
+
+    let (best, holdout) = (report.best().0, &report.holdout);
+    println!(
+        "held out: hp {} level {} 5/die {}  {:.1}%  {:.2}  [{:.2}, {:.2}]",
+        best.cash_out_hp,
+        best.target_level,
+        best.five_or_die,
+        holdout.rtp * 100.0,
+        holdout.mean_diff,
+        holdout.confidence_interval.0,
+        holdout.confidence_interval.1,
+    );
+
+    match report.is_exploitable() {
+        true => {
+            println!("RTP significantly exceeds 100%: ruleset has a positive-expectation strategy");
+            ExitCode::FAILURE
+        }
+        false => ExitCode::SUCCESS,
+    }
+}
@@ -0,0 +1,97 @@
+//! [`GameDataBuilder`] for setting up exact game states, mostly in tests.
+//!
+//! Pair a built bag with [`Script::in_bag_order`](crate::script::Script::in_bag_order)
+//! so pulls come out in the order given to [`GameDataBuilder::pull_order`].
+
+use crate::{GameData, OrbEffect};
+
+#[derive(Clone, Debug, Default)]
+pub struct GameDataBuilder {
+    game_data: GameData,
+    pull_order: Option<Vec<OrbEffect>>,
+}
+
+impl GameData {
+    pub fn builder() -> GameDataBuilder {
+        GameDataBuilder::default()
+    }
+}
+
+impl GameDataBuilder {
+    /// Also sets the milestone of `level`.
+    ///
+    /// # Panics
+    ///
+    /// If `level` is not in the milestone table.
+    pub fn level(mut self, level: u32) -> Self {
+        self.game_data.level = level;
+        self.game_data.milestone = GameData::MILESTONES[level as usize - 1];
+        self
+    }
+
+    pub fn points(mut self, points: u32) -> Self {
+        self.game_data.points = points;
+        self
+    }
+
+    pub fn hp(mut self, hp: u32) -> Self {
+        self.game_data.hp = hp;
+        self
+    }
+
+    pub fn max_hp(mut self, max_hp: u32) -> Self {
+        self.game_data.max_hp = max_hp;
+        self
+    }
+
+    /// In hundredths, like [`MULTIPLIER_SCALE`](crate::MULTIPLIER_SCALE).
+    pub fn multiplier(mut self, multiplier: u32) -> Self {
+        self.game_data.multiplier = multiplier;
+        self
+    }
+
+    pub fn glitch_chips(mut self, glitch_chips: u32) -> Self {
+        self.game_data.glitch_chips = glitch_chips;
+        self
+    }
+
+    pub fn bomb_immunity_turns(mut self, turns: u32) -> Self {
+        self.game_data.bomb_immunity_turns = turns;
+        self
+    }
+
+    /// Sets how many copies of catalogue orb `index` the bag holds.
+    pub fn orb_count(mut self, index: usize, count: u32) -> Self {
+        self.game_data.all_orbs[index].count = count;
+        self
+    }
+
+    /// The exact bag, listed in the order the orbs will be pulled. Without
+    /// one, the bag is filled from the catalogue counts.
+    pub fn pull_order(mut self, effects: Vec<OrbEffect>) -> Self {
+        self.pull_order = Some(effects);
+        self
+    }
+
+    pub fn sale_orbs(mut self, indices: Vec<usize>) -> Self {
+        self.game_data.sale_orbs_indices = indices;
+        self
+    }
+
+    pub fn build(self) -> GameData {
+        let mut game_data = self.game_data;
+        game_data.pullable_orb_effects = match self.pull_order {
+            // pulls come from the end of the bag
+            Some(mut effects) => {
+                effects.reverse();
+                effects
+            }
+            None => game_data
+                .all_orbs
+                .iter()
+                .flat_map(|orb| orb.to_orb_effects())
+                .collect(),
+        };
+        game_data
+    }
+}
@@ -0,0 +1,146 @@
+//! Commit-reveal randomness for off-chain play.
+//!
+//! The server draws a secret seed and salt from a CSPRNG and publishes
+//! `poseidon(seed, salt)` before the game starts. Every action then draws its
+//! randomness from `poseidon(seed, client_nonce, step)`, so the server cannot
+//! steer a pull without knowing the client's nonce and the client cannot
+//! predict one without the seed. The salt keeps the commitment from being
+//! brute-forced even if seeds were ever drawn from a small range. Once the
+//! game is over the seed and salt are revealed and anyone can re-run the
+//! whole log and check its payout with
+//! [`verify_committed_run`](crate::verify::verify_committed_run).
+
+use rand::rngs::StdRng;
+use rand::{CryptoRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt;
+use starknet_types_core::hash::{Poseidon, StarkHash};
+
+use crate::rules::Rules;
+use crate::{Action, ActionError, Game, perform_action_with_rules};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CommittedStep {
+    pub action: Action,
+    pub client_nonce: Felt,
+}
+
+/// The secret behind a commitment, published once the game is over.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reveal {
+    pub seed: Felt,
+    pub salt: Felt,
+}
+
+/// Server side of a committed game: holds the secret seed until game over.
+pub struct CommittedRun {
+    secret: Reveal,
+    commitment: Felt,
+    rules: Rules,
+    game: Game,
+    steps: Vec<CommittedStep>,
+}
+
+impl Default for CommittedRun {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommittedRun {
+    /// Draws the seed and salt from the thread-local CSPRNG.
+    pub fn new() -> Self {
+        Self::with_rng(&mut rand::rng())
+    }
+
+    /// Draws the seed and salt from `rng`, which must be cryptographically
+    /// secure: anyone who can predict the seed can predict every pull.
+    pub fn with_rng<R: CryptoRng + ?Sized>(rng: &mut R) -> Self {
+        let secret = Reveal {
+            seed: random_felt(rng),
+            salt: random_felt(rng),
+        };
+        CommittedRun {
+            secret,
+            commitment: seed_commitment(&secret),
+            rules: Rules::default(),
+            game: Game::New,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Starts the run under `rules` instead of the defaults. Verifiers need
+    /// the same rules to replay it.
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Published to the client before the first action.
+    pub fn commitment(&self) -> Felt {
+        self.commitment
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn steps(&self) -> &[CommittedStep] {
+        &self.steps
+    }
+
+    pub fn perform(&mut self, action: Action, client_nonce: Felt) -> Result<(), ActionError> {
+        let step = self.steps.len() as u64;
+        let mut rng = action_rng(&self.secret.seed, &client_nonce, step);
+        perform_action_with_rules(&mut self.game, action, &self.rules, &mut rng)?;
+        self.steps.push(CommittedStep {
+            action,
+            client_nonce,
+        });
+        Ok(())
+    }
+
+    /// The seed and salt, once the game is over and they can no longer
+    /// affect play.
+    pub fn reveal(&self) -> Option<Reveal> {
+        match self.game {
+            Game::GameOver { .. } => Some(self.secret),
+            _ => None,
+        }
+    }
+}
+
+/// Uniform over the 251-bit integers, all of which are valid felts.
+fn random_felt<R: Rng + ?Sized>(rng: &mut R) -> Felt {
+    let mut bytes: [u8; 32] = rng.random();
+    bytes[0] &= 0x07;
+    Felt::from_bytes_be(&bytes)
+}
+
+pub fn seed_commitment(reveal: &Reveal) -> Felt {
+    Poseidon::hash(&reveal.seed, &reveal.salt)
+}
+
+/// RNG for the `step`-th accepted action of a committed run.
+pub fn action_rng(seed: &Felt, client_nonce: &Felt, step: u64) -> StdRng {
+    let digest = Poseidon::hash_array(&[*seed, *client_nonce, Felt::from(step)]);
+    StdRng::from_seed(digest.to_bytes_be())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_draw_distinct_secrets() {
+        let (first, second) = (CommittedRun::new(), CommittedRun::new());
+        assert_ne!(first.secret.seed, second.secret.seed);
+        assert_ne!(first.secret.salt, second.secret.salt);
+        assert_ne!(first.commitment(), second.commitment());
+        assert_eq!(first.reveal(), None);
+    }
+}
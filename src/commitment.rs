@@ -0,0 +1,87 @@
+//! Poseidon commitments to game state.
+//!
+//! A commitment hashes the state tag together with the packed felt252 words
+//! from [`crate::encoding`], so two copies of the model holding the same state
+//! always get the same felt. The packing is the model's own layout, so an
+//! indexer or the chain cannot compute the same commitment from contract
+//! storage; use these for model-to-model checks only.
+
+use starknet_types_core::felt::Felt;
+use starknet_types_core::hash::{Poseidon, StarkHash};
+
+use crate::encoding::{EncodingError, pack};
+use crate::{Game, GameData};
+
+impl GameData {
+    /// Poseidon hash of the packed words.
+    pub fn commitment(&self) -> Result<Felt, EncodingError> {
+        Ok(Poseidon::hash_array(&pack(self)?))
+    }
+}
+
+impl Game {
+    /// Poseidon hash of the state tag followed by the state's packed words.
+    pub fn commitment(&self) -> Result<Felt, EncodingError> {
+        let mut felts = vec![Felt::from(self.kind() as u8)];
+        match self {
+            Game::New => {}
+            Game::Level { game_data }
+            | Game::LevelComplete { game_data }
+            | Game::FiveOrDiePhase { game_data }
+            | Game::Shop { game_data } => felts.extend(pack(game_data)?),
+            Game::GameOver {
+                game_data,
+                reason,
+                moonrocks_diff,
+            } => {
+                felts.push(Felt::from(*reason as u8));
+                felts.push(Felt::from(*moonrocks_diff));
+                felts.extend(pack(game_data)?);
+            }
+        }
+        Ok(Poseidon::hash_array(&felts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameOverReason, Stake};
+
+    #[test]
+    fn test_commitment_tracks_state() {
+        let game_data = GameData::new();
+        let level = Game::Level {
+            game_data: game_data.clone(),
+        };
+        let shop = Game::Shop {
+            game_data: game_data.clone(),
+        };
+        assert_eq!(level.commitment(), level.clone().commitment());
+        assert_ne!(level.commitment(), shop.commitment());
+
+        let mut changed = game_data.clone();
+        changed.hp -= 1;
+        assert_ne!(game_data.commitment(), changed.commitment());
+
+        let mut staked = game_data.clone();
+        staked.stake = Stake::X5;
+        assert_ne!(game_data.commitment(), staked.commitment());
+
+        let mut practice = game_data.clone();
+        practice.practice = true;
+        assert_ne!(game_data.commitment(), practice.commitment());
+
+        let cashed_out = Game::GameOver {
+            game_data: game_data.clone(),
+            reason: GameOverReason::CashedOut,
+            moonrocks_diff: 3,
+        };
+        let bag_empty = Game::GameOver {
+            game_data,
+            reason: GameOverReason::BagEmpty,
+            moonrocks_diff: 3,
+        };
+        assert_ne!(cashed_out.commitment(), bag_empty.commitment());
+    }
+}
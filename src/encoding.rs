@@ -0,0 +1,402 @@
+//! felt252 packing of [`GameData`], used for state commitments.
+//!
+//! This is the model's own layout, not the contract's.
+//!
+//! Fields are packed least significant bit first. Every word stays below 251
+//! bits so it is always a valid felt252.
+//!
+//! | word | contents                                                          |
+//! |------|-------------------------------------------------------------------|
+//! | 0    | level 8, points 32, milestone 32, hp 8, max_hp 8, multiplier 16   |
+//! |      | (hundredths), glitch_chips 32, moonrocks_spent 32,                |
+//! |      | moonrocks_earned 32, bomb_immunity_turns 8, pullable len 8,       |
+//! |      | pulled len 8, stake multiplier 8, practice 1                      |
+//! | 1    | orb counts, 8 bits per catalogue orb; then 6 shop slots, 5 bits   |
+//! |      | each holding a catalogue index or 31 when empty                   |
+//! | 2    | current orb prices, 11 bits per catalogue orb (0 if not buyable)  |
+//! | 3..  | bag, 5-bit catalogue indices, 50 per word: pullable then pulled   |
+
+use serde::Serialize;
+use starknet_types_core::felt::Felt;
+
+use crate::rules::Rules;
+use crate::summary::RunSummary;
+use crate::{Buyable, GameData, Orb, OrbEffect, Stake};
+
+const ORB_INDEX_BITS: u32 = 5;
+const EMPTY_SHOP_SLOT: u64 = (1 << ORB_INDEX_BITS) - 1;
+const SHOP_SLOTS: usize = 6;
+const COUNT_BITS: u32 = 8;
+const PRICE_BITS: u32 = 11;
+const BAG_ENTRIES_PER_WORD: usize = 50;
+const HEADER_WORDS: usize = 3;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub enum EncodingError {
+    FieldOverflow { field: &'static str, value: u64 },
+    EffectNotInCatalogue,
+    InvalidOrbIndex(u64),
+    MissingWords { expected: usize, found: usize },
+    InvalidStake(u64),
+}
+
+/// Packs `game_data` into felt252 words.
+pub fn pack(game_data: &GameData) -> Result<Vec<Felt>, EncodingError> {
+    let mut header = WordWriter::default();
+    header.write("level", game_data.level as u64, 8)?;
+    header.write("points", game_data.points as u64, 32)?;
+    header.write("milestone", game_data.milestone as u64, 32)?;
+    header.write("hp", game_data.hp as u64, 8)?;
+    header.write("max_hp", game_data.max_hp as u64, 8)?;
+    header.write("multiplier", game_data.multiplier as u64, 16)?;
+    header.write("glitch_chips", game_data.glitch_chips as u64, 32)?;
+    header.write("moonrocks_spent", game_data.moonrocks_spent as u64, 32)?;
+    header.write("moonrocks_earned", game_data.moonrocks_earned as u64, 32)?;
+    header.write(
+        "bomb_immunity_turns",
+        game_data.bomb_immunity_turns as u64,
+        8,
+    )?;
+    header.write(
+        "pullable_orb_effects",
+        game_data.pullable_orb_effects.len() as u64,
+        8,
+    )?;
+    header.write(
+        "pulled_orbs_effects",
+        game_data.pulled_orbs_effects.len() as u64,
+        8,
+    )?;
+    header.write("stake", game_data.stake.multiplier() as u64, 8)?;
+    header.write("practice", game_data.practice as u64, 1)?;
+
+    let mut orbs = WordWriter::default();
+    for orb in &game_data.all_orbs {
+        orbs.write("orb count", orb.count as u64, COUNT_BITS)?;
+    }
+    if game_data.sale_orbs_indices.len() > SHOP_SLOTS {
+        return Err(EncodingError::FieldOverflow {
+            field: "sale_orbs_indices",
+            value: game_data.sale_orbs_indices.len() as u64,
+        });
+    }
+    for slot in 0..SHOP_SLOTS {
+        let index = match game_data.sale_orbs_indices.get(slot) {
+            Some(index) => *index as u64,
+            None => EMPTY_SHOP_SLOT,
+        };
+        orbs.write("sale_orbs_indices", index, ORB_INDEX_BITS)?;
+    }
+
+    let mut prices = WordWriter::default();
+    for orb in &game_data.all_orbs {
+        let price = match orb.buyable {
+            Buyable::No => 0,
+            Buyable::Yes { current_price, .. } => current_price as u64,
+        };
+        prices.write("orb price", price, PRICE_BITS)?;
+    }
+
+    let mut words = vec![header.finish(), orbs.finish(), prices.finish()];
+
+    let bag = game_data
+        .pullable_orb_effects
+        .iter()
+        .chain(game_data.pulled_orbs_effects.iter())
+        .map(|effect| catalogue_index(&game_data.all_orbs, effect))
+        .collect::<Result<Vec<_>, _>>()?;
+    for chunk in bag.chunks(BAG_ENTRIES_PER_WORD) {
+        let mut word = WordWriter::default();
+        for index in chunk {
+            word.write("bag", *index as u64, ORB_INDEX_BITS)?;
+        }
+        words.push(word.finish());
+    }
+
+    Ok(words)
+}
+
+/// Rebuilds `GameData` from words produced by [`pack`]. Only orb counts and
+/// current prices are packed; effects, rarities and base prices come from
+/// `catalogue`, which must be the one the words were packed with.
+pub fn unpack(words: &[Felt], catalogue: &[Orb; 21]) -> Result<GameData, EncodingError> {
+    if words.len() < HEADER_WORDS {
+        return Err(EncodingError::MissingWords {
+            expected: HEADER_WORDS,
+            found: words.len(),
+        });
+    }
+
+    let mut header = WordReader::new(&words[0]);
+    let level = header.read(8) as u32;
+    let points = header.read(32) as u32;
+    let milestone = header.read(32) as u32;
+    let hp = header.read(8) as u32;
+    let max_hp = header.read(8) as u32;
+    let multiplier = header.read(16) as u32;
+    let glitch_chips = header.read(32) as u32;
+    let moonrocks_spent = header.read(32) as u32;
+    let moonrocks_earned = header.read(32) as u32;
+    let bomb_immunity_turns = header.read(8) as u32;
+    let pullable_len = header.read(8) as usize;
+    let pulled_len = header.read(8) as usize;
+    let stake = header.read(8);
+    let stake = Stake::from_multiplier(stake as u32).ok_or(EncodingError::InvalidStake(stake))?;
+    let practice = header.read(1) == 1;
+
+    let mut all_orbs = *catalogue;
+    let mut orbs = WordReader::new(&words[1]);
+    for orb in all_orbs.iter_mut() {
+        orb.count = orbs.read(COUNT_BITS) as u32;
+    }
+    let mut sale_orbs_indices = Vec::new();
+    for _ in 0..SHOP_SLOTS {
+        let index = orbs.read(ORB_INDEX_BITS);
+        match index {
+            EMPTY_SHOP_SLOT => {}
+            index if (index as usize) < all_orbs.len() => sale_orbs_indices.push(index as usize),
+            index => return Err(EncodingError::InvalidOrbIndex(index)),
+        }
+    }
+
+    let mut prices = WordReader::new(&words[2]);
+    for orb in all_orbs.iter_mut() {
+        let price = prices.read(PRICE_BITS) as u32;
+        if let Buyable::Yes { base_price, .. } = orb.buyable {
+            orb.buyable = Buyable::Yes {
+                base_price,
+                current_price: price,
+            };
+        }
+    }
+
+    let bag_len = pullable_len + pulled_len;
+    let bag_words = bag_len.div_ceil(BAG_ENTRIES_PER_WORD);
+    if words.len() < HEADER_WORDS + bag_words {
+        return Err(EncodingError::MissingWords {
+            expected: HEADER_WORDS + bag_words,
+            found: words.len(),
+        });
+    }
+    let mut bag = Vec::with_capacity(bag_len);
+    for (word_index, word) in words[HEADER_WORDS..HEADER_WORDS + bag_words]
+        .iter()
+        .enumerate()
+    {
+        let mut reader = WordReader::new(word);
+        let entries = (bag_len - word_index * BAG_ENTRIES_PER_WORD).min(BAG_ENTRIES_PER_WORD);
+        for _ in 0..entries {
+            let index = reader.read(ORB_INDEX_BITS);
+            let orb = all_orbs
+                .get(index as usize)
+                .ok_or(EncodingError::InvalidOrbIndex(index))?;
+            bag.push(orb.effect);
+        }
+    }
+    let pulled_orbs_effects = bag.split_off(pullable_len);
+
+    Ok(GameData {
+        level,
+        points,
+        milestone,
+        hp,
+        max_hp,
+        multiplier,
+        glitch_chips,
+        moonrocks_spent,
+        moonrocks_earned,
+        all_orbs,
+        sale_orbs_indices,
+        pullable_orb_effects: bag,
+        pulled_orbs_effects,
+        bomb_immunity_turns,
+        summary: RunSummary::default(),
+        rules: Rules::default(),
+        stake,
+        practice,
+    })
+}
+
+/// Names of the fields that differ between two snapshots of the model.
+pub fn mismatched_fields(left: &GameData, right: &GameData) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    let mut check = |name, equal: bool| {
+        if !equal {
+            fields.push(name);
+        }
+    };
+    check("level", left.level == right.level);
+    check("points", left.points == right.points);
+    check("milestone", left.milestone == right.milestone);
+    check("hp", left.hp == right.hp);
+    check("max_hp", left.max_hp == right.max_hp);
+    check("multiplier", left.multiplier == right.multiplier);
+    check("glitch_chips", left.glitch_chips == right.glitch_chips);
+    check(
+        "moonrocks_spent",
+        left.moonrocks_spent == right.moonrocks_spent,
+    );
+    check(
+        "moonrocks_earned",
+        left.moonrocks_earned == right.moonrocks_earned,
+    );
+    check("all_orbs", left.all_orbs == right.all_orbs);
+    check(
+        "sale_orbs_indices",
+        left.sale_orbs_indices == right.sale_orbs_indices,
+    );
+    check(
+        "pullable_orb_effects",
+        left.pullable_orb_effects == right.pullable_orb_effects,
+    );
+    check(
+        "pulled_orbs_effects",
+        left.pulled_orbs_effects == right.pulled_orbs_effects,
+    );
+    check(
+        "bomb_immunity_turns",
+        left.bomb_immunity_turns == right.bomb_immunity_turns,
+    );
+    check("stake", left.stake == right.stake);
+    check("practice", left.practice == right.practice);
+    fields
+}
+
+fn catalogue_index(all_orbs: &[Orb], effect: &OrbEffect) -> Result<usize, EncodingError> {
+    all_orbs
+        .iter()
+        .position(|orb| orb.effect == *effect)
+        .ok_or(EncodingError::EffectNotInCatalogue)
+}
+
+/// Appends fields to a 256-bit little-endian word.
+#[derive(Default)]
+struct WordWriter {
+    limbs: [u64; 4],
+    offset: u32,
+}
+
+impl WordWriter {
+    fn write(&mut self, field: &'static str, value: u64, width: u32) -> Result<(), EncodingError> {
+        if width < 64 && value >> width != 0 {
+            return Err(EncodingError::FieldOverflow { field, value });
+        }
+        debug_assert!(self.offset + width <= 251, "word layout exceeds felt252");
+
+        let limb = (self.offset / 64) as usize;
+        let shift = self.offset % 64;
+        self.limbs[limb] |= value << shift;
+        if shift + width > 64 {
+            self.limbs[limb + 1] |= value >> (64 - shift);
+        }
+        self.offset += width;
+        Ok(())
+    }
+
+    fn finish(self) -> Felt {
+        let mut bytes = [0u8; 32];
+        for (chunk, limb) in bytes.chunks_exact_mut(8).zip(self.limbs) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        Felt::from_bytes_le(&bytes)
+    }
+}
+
+/// Reads fields back out of a word in the order they were written.
+struct WordReader {
+    limbs: [u64; 4],
+    offset: u32,
+}
+
+impl WordReader {
+    fn new(word: &Felt) -> Self {
+        WordReader {
+            limbs: word.to_le_digits(),
+            offset: 0,
+        }
+    }
+
+    fn read(&mut self, width: u32) -> u64 {
+        let limb = (self.offset / 64) as usize;
+        let shift = self.offset % 64;
+        let mut value = self.limbs[limb] >> shift;
+        if shift + width > 64 {
+            value |= self.limbs[limb + 1] << (64 - shift);
+        }
+        self.offset += width;
+        match width {
+            64 => value,
+            _ => value & ((1 << width) - 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_round_trip() {
+        let mut game_data = GameData::new();
+        game_data.points = 17;
+        game_data.multiplier = 250;
+        game_data.glitch_chips = 40;
+        game_data.all_orbs[11].count = 2;
+        game_data.all_orbs[11].buyable = Buyable::Yes {
+            base_price: 9,
+            current_price: 11,
+        };
+        game_data.sale_orbs_indices = vec![4, 7, 11, 13, 15, 20];
+        game_data.stake = Stake::X25;
+        game_data.practice = true;
+        let pulled = game_data.pullable_orb_effects.pop().unwrap();
+        game_data.pulled_orbs_effects.push(pulled);
+
+        let words = pack(&game_data).unwrap();
+        assert_eq!(words.len(), HEADER_WORDS + 1);
+        let unpacked = unpack(&words, &Orb::all_orbs()).unwrap();
+        assert!(mismatched_fields(&game_data, &unpacked).is_empty());
+    }
+
+    #[test]
+    fn test_unpack_uses_given_catalogue() {
+        let mut catalogue = Orb::all_orbs();
+        catalogue[5].effect = OrbEffect::GlitchChips(30);
+        catalogue[5].count = 2;
+        let mut game_data = GameData::new();
+        game_data.set_catalogue(catalogue);
+
+        let words = pack(&game_data).unwrap();
+        let unpacked = unpack(&words, &catalogue).unwrap();
+        assert!(mismatched_fields(&game_data, &unpacked).is_empty());
+        assert!(
+            unpacked
+                .pullable_orb_effects
+                .contains(&OrbEffect::GlitchChips(30))
+        );
+    }
+
+    #[test]
+    fn test_unpack_rejects_unknown_stake() {
+        let mut words = pack(&GameData::new()).unwrap();
+        // the stake multiplier sits at bit 224 of the header word
+        words[0] += Felt::from(2u8) * Felt::TWO.pow(224u32);
+        assert_eq!(
+            unpack(&words, &Orb::all_orbs()),
+            Err(EncodingError::InvalidStake(3))
+        );
+    }
+
+    #[test]
+    fn test_pack_rejects_overflow() {
+        let mut game_data = GameData::new();
+        game_data.hp = 256;
+        assert_eq!(
+            pack(&game_data),
+            Err(EncodingError::FieldOverflow {
+                field: "hp",
+                value: 256
+            })
+        );
+    }
+}
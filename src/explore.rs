@@ -0,0 +1,362 @@
+//! What-if exploration: every outcome of an action with its probability.
+//!
+//! Nothing here mutates the game passed in. Pulls are enumerated per distinct
+//! orb effect in the bag, FiveOrDie phases per sequence of up to five pulls
+//! (stopping early where the phase ends), and shops per combination of
+//! offered orbs. The order of `pullable_orb_effects` in an outcome is not
+//! meaningful since the bag is reshuffled before every pull.
+
+use serde::Serialize;
+
+use crate::{
+    Action, ActionError, Game, GameData, MAX_LEVEL, OrbEffect, SHOP_SLOTS_PER_RARITY,
+    five_or_die_pull, open_shop, perform_action_with_rng, pull_last_orb, shop_candidates,
+    take_five_or_die_orbs,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Outcome {
+    pub probability: f64,
+    pub game: Game,
+}
+
+/// All outcomes of performing `action` on `game`. Probabilities sum to 1.
+pub fn explore(game: &Game, action: Action) -> Result<Vec<Outcome>, ActionError> {
+    match (game, action) {
+        (Game::Level { game_data }, Action::PullOrb) => Ok(explore_pull(game_data)),
+        (Game::FiveOrDiePhase { game_data }, Action::ConfirmFiveOrDie(true)) => {
+            let mut game_data = game_data.clone();
+            let fiveordie_orbs = take_five_or_die_orbs(&mut game_data);
+            let mut outcomes = Vec::new();
+            explore_five_or_die(game_data, &fiveordie_orbs, 5, false, 1.0, &mut outcomes);
+            Ok(outcomes)
+        }
+        (Game::LevelComplete { game_data }, Action::EnterShop) if game_data.level < MAX_LEVEL => {
+            Ok(explore_shop(game_data))
+        }
+        _ => {
+            // every other transition is deterministic, or rejected
+            let mut game = game.clone();
+            perform_action_with_rng(&mut game, action, &mut rand::rng())?;
+            Ok(vec![Outcome {
+                probability: 1.0,
+                game,
+            }])
+        }
+    }
+}
+
+/// Distinct effects in the bag with how many orbs carry each.
+fn distinct_effects(bag: &[OrbEffect]) -> Vec<(OrbEffect, usize)> {
+    let mut effects: Vec<(OrbEffect, usize)> = Vec::new();
+    for effect in bag {
+        match effects.iter_mut().find(|(seen, _)| seen == effect) {
+            Some((_, count)) => *count += 1,
+            None => effects.push((*effect, 1)),
+        }
+    }
+    effects
+}
+
+/// `game_data` with one orb carrying `effect` moved to the end of the bag,
+/// where the next pull takes it from.
+fn with_effect_on_top(game_data: &GameData, effect: &OrbEffect) -> GameData {
+    let mut game_data = game_data.clone();
+    let bag = &mut game_data.pullable_orb_effects;
+    if let Some(index) = bag.iter().position(|candidate| candidate == effect) {
+        let orb = bag.remove(index);
+        bag.push(orb);
+    }
+    game_data
+}
+
+fn explore_pull(game_data: &GameData) -> Vec<Outcome> {
+    let bag_size = game_data.pullable_orb_effects.len();
+    if bag_size == 0 {
+        return vec![Outcome {
+            probability: 1.0,
+            game: pull_last_orb(game_data.clone()),
+        }];
+    }
+
+    distinct_effects(&game_data.pullable_orb_effects)
+        .into_iter()
+        .map(|(effect, count)| Outcome {
+            probability: count as f64 / bag_size as f64,
+            game: pull_last_orb(with_effect_on_top(game_data, &effect)),
+        })
+        .collect()
+}
+
+/// The phase shuffles the bag once, so its pulls are uniform over the bag
+/// except right after a `PointRewind` put a point orb back on top: when
+/// `rewound`, that orb is the next pull.
+fn explore_five_or_die(
+    game_data: GameData,
+    fiveordie_orbs: &[OrbEffect],
+    pulls_left: u32,
+    rewound: bool,
+    probability: f64,
+    outcomes: &mut Vec<Outcome>,
+) {
+    if pulls_left == 0 {
+        let mut game_data = game_data;
+        game_data
+            .pullable_orb_effects
+            .extend(fiveordie_orbs.iter().copied());
+        outcomes.push(Outcome {
+            probability,
+            game: Game::Level { game_data },
+        });
+        return;
+    }
+
+    let bag_size = game_data.pullable_orb_effects.len();
+    if bag_size == 0 {
+        let mut game_data = game_data;
+        let game = five_or_die_pull(&mut game_data, fiveordie_orbs)
+            .expect("pulling from an empty bag ends the phase");
+        outcomes.push(Outcome { probability, game });
+        return;
+    }
+
+    let draws: Vec<(GameData, f64)> = match rewound {
+        true => vec![(game_data, probability)],
+        false => distinct_effects(&game_data.pullable_orb_effects)
+            .into_iter()
+            .map(|(effect, count)| {
+                (
+                    with_effect_on_top(&game_data, &effect),
+                    probability * count as f64 / bag_size as f64,
+                )
+            })
+            .collect(),
+    };
+    for (mut next, probability) in draws {
+        let pulls_rewind = next.pullable_orb_effects.last() == Some(&OrbEffect::PointRewind);
+        let bag_size = next.pullable_orb_effects.len();
+        match five_or_die_pull(&mut next, fiveordie_orbs) {
+            Some(game) => outcomes.push(Outcome { probability, game }),
+            None => {
+                // a rewind that found a point orb leaves the bag as large as before
+                let rewound = pulls_rewind && next.pullable_orb_effects.len() == bag_size;
+                explore_five_or_die(
+                    next,
+                    fiveordie_orbs,
+                    pulls_left - 1,
+                    rewound,
+                    probability,
+                    outcomes,
+                )
+            }
+        }
+    }
+}
+
+fn explore_shop(game_data: &GameData) -> Vec<Outcome> {
+    let mut offers = vec![Vec::new()];
+    for (candidates, slots) in shop_candidates(game_data).iter().zip(SHOP_SLOTS_PER_RARITY) {
+        // short rarities fill fewer slots, like the shop itself
+        let picks = combinations(candidates, slots.min(candidates.len()));
+        offers = offers
+            .iter()
+            .flat_map(|offer| {
+                picks.iter().map(move |pick| {
+                    let mut offer = offer.clone();
+                    offer.extend(pick);
+                    offer
+                })
+            })
+            .collect();
+    }
+
+    let probability = 1.0 / offers.len() as f64;
+    offers
+        .into_iter()
+        .map(|offer| Outcome {
+            probability,
+            game: open_shop(game_data, offer),
+        })
+        .collect()
+}
+
+/// All `k`-element subsets of `items`, each in the order of `items`.
+fn combinations(items: &[usize], k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    if items.len() < k {
+        return Vec::new();
+    }
+    let mut result: Vec<Vec<usize>> = combinations(&items[1..], k - 1)
+        .into_iter()
+        .map(|mut rest| {
+            rest.insert(0, items[0]);
+            rest
+        })
+        .collect();
+    result.extend(combinations(&items[1..], k));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::Script;
+    use crate::{ActionKind, Buyable, GameStateKind, perform_action_with_rng};
+
+    fn total_probability(outcomes: &[Outcome]) -> f64 {
+        outcomes.iter().map(|outcome| outcome.probability).sum()
+    }
+
+    #[test]
+    fn test_explore_pull() {
+        let game = Game::Level {
+            game_data: GameData::new(),
+        };
+        let outcomes = explore(&game, Action::PullOrb).unwrap();
+
+        // 11 orbs: bombs 1 (x2), 2, 3, point per orb, point 5 (x3), point per bomb,
+        // health and multiplier
+        assert_eq!(outcomes.len(), 8);
+        assert!((total_probability(&outcomes) - 1.0).abs() < 1e-9);
+        let two_bombs = outcomes
+            .iter()
+            .find(|outcome| match &outcome.game {
+                Game::Level { game_data } => game_data.hp == 4,
+                _ => false,
+            })
+            .unwrap();
+        assert!((two_bombs.probability - 2.0 / 11.0).abs() < 1e-9);
+
+        // the original is untouched
+        assert_eq!(
+            game,
+            Game::Level {
+                game_data: GameData::new()
+            }
+        );
+    }
+
+    #[test]
+    fn test_explore_five_or_die_and_shop() {
+        let mut game_data = GameData::new();
+        game_data.pullable_orb_effects.push(OrbEffect::FiveOrDie);
+        let game = Game::FiveOrDiePhase { game_data };
+        let outcomes = explore(&game, Action::ConfirmFiveOrDie(true)).unwrap();
+        assert!((total_probability(&outcomes) - 1.0).abs() < 1e-9);
+
+        let game = Game::LevelComplete {
+            game_data: GameData::new(),
+        };
+        let outcomes = explore(&game, Action::EnterShop).unwrap();
+        // C(9, 3) * C(5, 2) * C(3, 1)
+        assert_eq!(outcomes.len(), 84 * 10 * 3);
+        assert!((total_probability(&outcomes) - 1.0).abs() < 1e-9);
+
+        assert_eq!(
+            explore(&game, Action::PullOrb),
+            Err(ActionError::InvalidAction {
+                state: GameStateKind::LevelComplete,
+                action: ActionKind::PullOrb,
+            })
+        );
+    }
+
+    /// Every ordering of `items`, duplicates included.
+    fn permutations(items: &[OrbEffect]) -> Vec<Vec<OrbEffect>> {
+        if items.is_empty() {
+            return vec![Vec::new()];
+        }
+        let mut result = Vec::new();
+        for index in 0..items.len() {
+            let mut rest = items.to_vec();
+            let first = rest.remove(index);
+            for mut permutation in permutations(&rest) {
+                permutation.insert(0, first);
+                result.push(permutation);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_explore_five_or_die_follows_point_rewind() {
+        let bag = [
+            OrbEffect::Point(5),
+            OrbEffect::PointRewind,
+            OrbEffect::Bomb(1),
+            OrbEffect::Bomb(1),
+            OrbEffect::Bomb(1),
+            OrbEffect::Bomb(1),
+            OrbEffect::Point(1),
+        ];
+        let summary = |game: &Game| match game {
+            Game::Level { game_data }
+            | Game::LevelComplete { game_data }
+            | Game::GameOver { game_data, .. } => (game.kind(), game_data.points, game_data.hp),
+            _ => panic!("the phase ends in a level, a completed level or game over"),
+        };
+
+        // the real phase over every order the shuffle could leave the bag in
+        let orders = permutations(&bag);
+        let mut expected: Vec<((GameStateKind, u32, u32), f64)> = Vec::new();
+        for order in &orders {
+            let game_data = GameData::builder().hp(5).pull_order(order.clone()).build();
+            let mut game = Game::FiveOrDiePhase { game_data };
+            perform_action_with_rng(
+                &mut game,
+                Action::ConfirmFiveOrDie(true),
+                &mut Script::in_bag_order(),
+            )
+            .unwrap();
+            match expected
+                .iter_mut()
+                .find(|(seen, _)| *seen == summary(&game))
+            {
+                Some((_, probability)) => *probability += 1.0 / orders.len() as f64,
+                None => expected.push((summary(&game), 1.0 / orders.len() as f64)),
+            }
+        }
+
+        let game_data = GameData::builder().hp(5).pull_order(bag.to_vec()).build();
+        let outcomes = explore(
+            &Game::FiveOrDiePhase { game_data },
+            Action::ConfirmFiveOrDie(true),
+        )
+        .unwrap();
+        for (key, probability) in expected {
+            let explored: f64 = outcomes
+                .iter()
+                .filter(|outcome| summary(&outcome.game) == key)
+                .map(|outcome| outcome.probability)
+                .sum();
+            assert!((explored - probability).abs() < 1e-9, "{key:?}");
+        }
+    }
+
+    #[test]
+    fn test_explore_shop_matches_shop_rules() {
+        let game_data = GameData::builder().level(MAX_LEVEL).build();
+        let game = Game::LevelComplete { game_data };
+        assert_eq!(
+            explore(&game, Action::EnterShop),
+            Err(ActionError::NoNextLevel)
+        );
+
+        // a single buyable rare orb fills one of the two rare slots
+        let mut game_data = GameData::new();
+        for index in 14..=17 {
+            game_data.all_orbs[index].buyable = Buyable::No;
+        }
+        let game = Game::LevelComplete { game_data };
+        let outcomes = explore(&game, Action::EnterShop).unwrap();
+        // C(9, 3) * C(1, 1) * C(3, 1)
+        assert_eq!(outcomes.len(), 84 * 3);
+        assert!((total_probability(&outcomes) - 1.0).abs() < 1e-9);
+        assert!(outcomes.iter().all(|outcome| match &outcome.game {
+            Game::Shop { game_data } => game_data.sale_orbs_indices.contains(&13),
+            _ => false,
+        }));
+    }
+}
@@ -0,0 +1,112 @@
+//! Bounded snapshot history behind [`GameSession`](crate::session::GameSession)'s
+//! undo, redo and jump (`dev-tools` only).
+
+use std::collections::VecDeque;
+
+use crate::{Action, Game};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct History {
+    /// Oldest kept state first; the last entry is the current state.
+    states: VecDeque<Game>,
+    /// Undone actions with the state each one led to, latest undo last.
+    redo: Vec<(Action, Game)>,
+}
+
+impl History {
+    /// Number of past turns kept for undo.
+    pub const LIMIT: usize = 64;
+
+    pub fn undo_depth(&self) -> usize {
+        self.states.len().saturating_sub(1)
+    }
+
+    pub fn redo_depth(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Remembers the state before the first tracked action.
+    pub(crate) fn start(&mut self, game: &Game) {
+        if self.states.is_empty() {
+            self.states.push_back(game.clone());
+        }
+    }
+
+    /// Remembers the state an action led to; a new action drops the redo branch.
+    pub(crate) fn record(&mut self, game: &Game) {
+        self.states.push_back(game.clone());
+        if self.states.len() > Self::LIMIT + 1 {
+            self.states.pop_front();
+        }
+        self.redo.clear();
+    }
+
+    pub(crate) fn undo(&mut self, actions: &mut Vec<Action>) -> Option<Game> {
+        if self.undo_depth() == 0 {
+            return None;
+        }
+        let current = self.states.pop_back()?;
+        let action = actions.pop()?;
+        self.redo.push((action, current));
+        self.states.back().cloned()
+    }
+
+    pub(crate) fn redo(&mut self, actions: &mut Vec<Action>) -> Option<Game> {
+        let (action, game) = self.redo.pop()?;
+        actions.push(action);
+        self.states.push_back(game.clone());
+        Some(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+    use crate::session::GameSession;
+    use crate::{Action, Game, Stake};
+
+    #[test]
+    fn test_undo_redo_and_branch() {
+        let mut session = GameSession::new(1, "designer", 8);
+        session
+            .perform_action(Action::StartGame(Stake::X1))
+            .unwrap();
+        let started = session.game().clone();
+        session.perform_action(Action::PullOrb).unwrap();
+        let pulled = session.game().clone();
+
+        assert!(session.undo());
+        assert_eq!(session.game(), &started);
+        assert!(session.redo());
+        assert_eq!(session.game(), &pulled);
+        assert!(!session.redo());
+
+        assert!(session.jump_to(0));
+        assert_eq!(session.game(), &Game::New);
+        assert!(session.jump_to(2));
+        assert_eq!(session.game(), &pulled);
+        assert!(!session.jump_to(3));
+
+        // the same turn replays the same bag order
+        assert!(session.undo());
+        session.perform_action(Action::PullOrb).unwrap();
+        assert_eq!(session.game(), &pulled);
+        assert_eq!(session.nonce(), 10);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut history = History::default();
+        let mut actions = Vec::new();
+        history.start(&Game::New);
+        for _ in 0..History::LIMIT + 10 {
+            actions.push(Action::PullOrb);
+            history.record(&Game::New);
+        }
+
+        assert_eq!(history.undo_depth(), History::LIMIT);
+        while history.undo(&mut actions).is_some() {}
+        assert_eq!(actions.len(), 10);
+        assert_eq!(history.redo_depth(), History::LIMIT);
+    }
+}
@@ -1,16 +1,73 @@
-use rand::seq::{IndexedRandom, SliceRandom};
+use std::fmt;
 
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::rules::Rules;
+use crate::summary::{RunSummary, ShopSummary};
+
+pub mod builder;
+pub mod commit_reveal;
+pub mod commitment;
+pub mod encoding;
+pub mod explore;
+#[cfg(feature = "dev-tools")]
+pub mod history;
+pub mod rpc;
+pub mod rtp;
+pub mod rules;
+pub mod script;
+pub mod session;
+pub mod settlement;
+pub mod store;
+pub mod summary;
+pub mod trace;
+pub mod tutorial;
+pub mod verify;
+pub mod wallet;
+
+/// Bumped whenever a rule change makes old action logs replay differently.
+pub const RULESET_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum Game {
+    #[default]
     New,
-    Level { game_data: GameData },
-    LevelComplete { game_data: GameData },
-    FiveOrDiePhase { game_data: GameData },
-    Shop { game_data: GameData },
-    GameOver { moonrocks_diff: i32 },
+    Level {
+        game_data: GameData,
+    },
+    LevelComplete {
+        game_data: GameData,
+    },
+    FiveOrDiePhase {
+        game_data: GameData,
+    },
+    Shop {
+        game_data: GameData,
+    },
+    GameOver {
+        game_data: GameData,
+        reason: GameOverReason,
+        moonrocks_diff: i32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameOverReason {
+    CashedOut,
+    HpDepleted,
+    BagEmpty,
+    /// Died or ran out of orbs during the five FiveOrDie pulls.
+    FiveOrDieFailed,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Action {
-    StartGame,
+    StartGame(Stake),
+    /// Starts a practice run: nothing is charged and nothing is paid out.
+    StartPractice,
     PullOrb,
     CashOut,
     EnterShop,
@@ -19,6 +76,83 @@ pub enum Action {
     GoToNextLevel,
 }
 
+/// Which state a [`Game`] is in, without its data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameStateKind {
+    New,
+    Level,
+    LevelComplete,
+    FiveOrDiePhase,
+    Shop,
+    GameOver,
+}
+
+impl Game {
+    /// Starts a run at `stake` played under `rules`. [`Action::StartGame`]
+    /// starts one under the rules passed to [`perform_action_with_rules`].
+    pub fn start(rules: Rules, stake: Stake) -> Game {
+        let mut game_data = GameData {
+            rules,
+            stake,
+            ..GameData::new()
+        };
+        game_data.moonrocks_spent = game_data.opening_spend() + game_data.level_cost(0);
+        Game::Level { game_data }
+    }
+
+    /// Starts a practice run under `rules`, free of any moonrock accounting.
+    pub fn start_practice(rules: Rules) -> Game {
+        Game::Level {
+            game_data: GameData {
+                rules,
+                practice: true,
+                moonrocks_spent: 0,
+                ..GameData::new()
+            },
+        }
+    }
+
+    pub fn kind(&self) -> GameStateKind {
+        match self {
+            Game::New => GameStateKind::New,
+            Game::Level { .. } => GameStateKind::Level,
+            Game::LevelComplete { .. } => GameStateKind::LevelComplete,
+            Game::FiveOrDiePhase { .. } => GameStateKind::FiveOrDiePhase,
+            Game::Shop { .. } => GameStateKind::Shop,
+            Game::GameOver { .. } => GameStateKind::GameOver,
+        }
+    }
+}
+
+/// Which [`Action`] was sent, without its arguments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ActionKind {
+    StartGame,
+    StartPractice,
+    PullOrb,
+    CashOut,
+    EnterShop,
+    BuyOrb,
+    ConfirmFiveOrDie,
+    GoToNextLevel,
+}
+
+impl Action {
+    pub fn kind(&self) -> ActionKind {
+        match self {
+            Action::StartGame(_) => ActionKind::StartGame,
+            Action::StartPractice => ActionKind::StartPractice,
+            Action::PullOrb => ActionKind::PullOrb,
+            Action::CashOut => ActionKind::CashOut,
+            Action::EnterShop => ActionKind::EnterShop,
+            Action::BuyOrb(_) => ActionKind::BuyOrb,
+            Action::ConfirmFiveOrDie(_) => ActionKind::ConfirmFiveOrDie,
+            Action::GoToNextLevel => ActionKind::GoToNextLevel,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum InShopSlot {
     One,
     Two,
@@ -28,19 +162,131 @@ pub enum InShopSlot {
     Six,
 }
 
+/// Scales every level charge and the payout of a run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Stake {
+    #[default]
+    X1,
+    X5,
+    X25,
+}
+
+impl Stake {
+    pub const ALL: [Stake; 3] = [Stake::X1, Stake::X5, Stake::X25];
+
+    pub fn multiplier(self) -> u32 {
+        match self {
+            Stake::X1 => 1,
+            Stake::X5 => 5,
+            Stake::X25 => 25,
+        }
+    }
+
+    pub fn from_multiplier(multiplier: u32) -> Option<Stake> {
+        Self::ALL
+            .into_iter()
+            .find(|stake| stake.multiplier() == multiplier)
+    }
+}
+
+impl InShopSlot {
+    pub const ALL: [InShopSlot; 6] = [
+        InShopSlot::One,
+        InShopSlot::Two,
+        InShopSlot::Three,
+        InShopSlot::Four,
+        InShopSlot::Five,
+        InShopSlot::Six,
+    ];
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum ActionError {
-    InvalidActionInNewGame,
-    InvalidActionInLevel,
-    InvalidActionInLevelComplete,
-    InvalidActionInFiveOrDiePhase,
+    /// `action` is not allowed in `state`.
+    InvalidAction {
+        state: GameStateKind,
+        action: ActionKind,
+    },
     MilestoneNotMetYet,
     NoPointsToCashOut,
-    InvalidActionInShop,
-    OrbTooExpensive,
+    /// The final level is complete; the only way on is to cash out.
+    NoNextLevel,
+    /// The shop has fewer orbs on sale than the slot asked for.
+    EmptyShopSlot(InShopSlot),
+    OrbTooExpensive {
+        price: u32,
+        chips: u32,
+    },
     BrokenErrorNonBuyableInShop,
-    GameOver,
+    InsufficientMoonrocks {
+        cost: u32,
+        balance: u32,
+    },
+    StaleState {
+        expected: u64,
+        current: u64,
+    },
+}
+
+/// Reads as the verb phrase after "cannot".
+impl fmt::Display for ActionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ActionKind::StartGame => "start a game",
+            ActionKind::StartPractice => "start a practice run",
+            ActionKind::PullOrb => "pull an orb",
+            ActionKind::CashOut => "cash out",
+            ActionKind::EnterShop => "enter the shop",
+            ActionKind::BuyOrb => "buy an orb",
+            ActionKind::ConfirmFiveOrDie => "answer FiveOrDie",
+            ActionKind::GoToNextLevel => "go to the next level",
+        })
+    }
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionError::InvalidAction { state, action } => {
+                let when = match state {
+                    GameStateKind::New => "before the game has started",
+                    GameStateKind::Level => "during a level",
+                    GameStateKind::LevelComplete => "after completing a level",
+                    GameStateKind::FiveOrDiePhase => "while a FiveOrDie decision is pending",
+                    GameStateKind::Shop => "in the shop",
+                    GameStateKind::GameOver => "after the game is over",
+                };
+                write!(f, "cannot {action} {when}")
+            }
+            ActionError::MilestoneNotMetYet => write!(f, "the level milestone is not met yet"),
+            ActionError::NoPointsToCashOut => write!(f, "there are no points to cash out"),
+            ActionError::NoNextLevel => {
+                write!(f, "level {MAX_LEVEL} is the last level, cash out instead")
+            }
+            ActionError::EmptyShopSlot(slot) => {
+                write!(f, "shop slot {} is empty", *slot as usize + 1)
+            }
+            ActionError::OrbTooExpensive { price, chips } => write!(
+                f,
+                "orb costs {price} glitch chips and only {chips} are available"
+            ),
+            ActionError::BrokenErrorNonBuyableInShop => {
+                write!(f, "the shop offered an orb that cannot be bought")
+            }
+            ActionError::InsufficientMoonrocks { cost, balance } => write!(
+                f,
+                "action costs {cost} moonrocks and the balance is {balance}"
+            ),
+            ActionError::StaleState { expected, current } => write!(
+                f,
+                "action was built against nonce {expected} but the game is at nonce {current}"
+            ),
+        }
+    }
 }
 
+impl std::error::Error for ActionError {}
+
 enum OrbEffectResult {
     Continue,
     GameStateChanged,
@@ -72,9 +318,22 @@ fn apply_orb_effect(
     }
 }
 
+/// Multipliers are fixed-point in hundredths, so `150` is 1.5x.
+pub const MULTIPLIER_SCALE: u32 = 100;
+
+/// Applies a fixed-point multiplier to `points`, rounding down like the
+/// contract's integer division.
+pub fn apply_multiplier(points: u32, multiplier: u32) -> u32 {
+    (points as u64 * multiplier as u64 / MULTIPLIER_SCALE as u64) as u32
+}
+
+/// Price of the next copy of an orb: 20% more, rounded up.
+pub fn increased_price(current_price: u32) -> u32 {
+    (current_price * 6).div_ceil(5)
+}
+
 fn handle_point_effect(points: u32, game_data: &mut GameData) -> OrbEffectResult {
-    let total_points = (points as f32 * game_data.multiplier).floor() as u32;
-    game_data.points += total_points;
+    game_data.points += apply_multiplier(points, game_data.multiplier);
     OrbEffectResult::Continue
 }
 
@@ -82,9 +341,8 @@ fn handle_point_per_orb_remaining_effect(
     point_per_orb: u32,
     game_data: &mut GameData,
 ) -> OrbEffectResult {
-    let num_orbs = game_data.pullable_orb_effects.iter().count() as f32;
-    let total_points = (num_orbs * point_per_orb as f32 * game_data.multiplier).floor() as u32;
-    game_data.points += total_points;
+    let num_orbs = game_data.pullable_orb_effects.len() as u32;
+    game_data.points += apply_multiplier(num_orbs * point_per_orb, game_data.multiplier);
     OrbEffectResult::Continue
 }
 
@@ -95,19 +353,16 @@ fn handle_point_per_bomb_pulled_effect(
     let bombs_pulled = game_data
         .pulled_orbs_effects
         .iter()
-        .filter(|effect| match effect {
-            OrbEffect::Bomb(_) => true,
-            _ => false,
-        })
-        .count() as f32;
-    let total_points =
-        (bombs_pulled * point_per_bomb_pulled as f32 * game_data.multiplier).floor() as u32;
-    game_data.points += total_points;
+        .filter(|effect| matches!(effect, OrbEffect::Bomb(_)))
+        .count() as u32;
+    game_data.points +=
+        apply_multiplier(bombs_pulled * point_per_bomb_pulled, game_data.multiplier);
     OrbEffectResult::Continue
 }
 
 fn handle_glitch_chips_effect(glitch_chips: u32, game_data: &mut GameData) -> OrbEffectResult {
     game_data.glitch_chips += glitch_chips;
+    game_data.summary.level_mut(game_data.level).chips_earned += glitch_chips;
     OrbEffectResult::Continue
 }
 
@@ -123,15 +378,18 @@ fn handle_health_effect(healing: u32, game_data: &mut GameData) -> OrbEffectResu
 
 fn handle_bomb_effect(damage: u32, game_data: &mut GameData) -> OrbEffectResult {
     if game_data.bomb_immunity_turns == 0 {
+        game_data.summary.damage_taken += damage.min(game_data.hp);
         game_data.hp = match game_data.hp > damage {
             true => game_data.hp - damage,
             false => 0,
         }
+    } else {
+        game_data.summary.damage_blocked += damage;
     }
     OrbEffectResult::Continue
 }
 
-fn handle_multiplier_effect(additional_mult: f32, game_data: &mut GameData) -> OrbEffectResult {
+fn handle_multiplier_effect(additional_mult: u32, game_data: &mut GameData) -> OrbEffectResult {
     game_data.multiplier += additional_mult;
     OrbEffectResult::Continue
 }
@@ -141,11 +399,11 @@ fn handle_point_rewind_effect(game_data: &mut GameData) -> OrbEffectResult {
     let mut lowest_point_value = 777;
 
     for (index, effect) in game_data.pulled_orbs_effects.iter().enumerate() {
-        if let OrbEffect::Point(points) = effect {
-            if *points < lowest_point_value {
-                lowest_point_value = *points;
-                lowest_point_index = Some(index);
-            }
+        if let OrbEffect::Point(points) = effect
+            && *points < lowest_point_value
+        {
+            lowest_point_value = *points;
+            lowest_point_index = Some(index);
         }
     }
     if let Some(index) = lowest_point_index {
@@ -167,11 +425,24 @@ fn handle_bomb_immunity_effect(turns: u32, game_data: &mut GameData) -> OrbEffec
     OrbEffectResult::Continue
 }
 
-fn handle_five_or_die_pulls(mut game_data: GameData) -> Game {
-    let mut rng = rand::rng();
-    game_data.pullable_orb_effects.shuffle(&mut rng);
+fn handle_five_or_die_pulls<R: Randomness + ?Sized>(mut game_data: GameData, rng: &mut R) -> Game {
+    let pulls_so_far = game_data.summary.total_pulls;
+    rng.shuffle_bag(&mut game_data.pullable_orb_effects, pulls_so_far);
+    let fiveordie_orbs = take_five_or_die_orbs(&mut game_data);
+
+    for _ in 0..5 {
+        if let Some(game) = five_or_die_pull(&mut game_data, &fiveordie_orbs) {
+            return game;
+        }
+    }
+
+    // Add the FiveOrDie orbs back to pullable effects
+    game_data.pullable_orb_effects.extend(fiveordie_orbs);
+    Game::Level { game_data }
+}
 
-    // Temporarily remove all FiveOrDie orbs to prevent infinite loops
+/// Temporarily removes all FiveOrDie orbs to prevent infinite loops.
+fn take_five_or_die_orbs(game_data: &mut GameData) -> Vec<OrbEffect> {
     let mut fiveordie_orbs = Vec::new();
     game_data.pullable_orb_effects.retain(|effect| {
         if matches!(effect, OrbEffect::FiveOrDie) {
@@ -181,177 +452,229 @@ fn handle_five_or_die_pulls(mut game_data: GameData) -> Game {
             true
         }
     });
+    fiveordie_orbs
+}
 
-    let mut effects_applied = 0;
+/// One pull of the FiveOrDie phase, from the end of the bag. Returns the
+/// resulting state if the pull ended the phase.
+fn five_or_die_pull(game_data: &mut GameData, fiveordie_orbs: &[OrbEffect]) -> Option<Game> {
+    let Some(effect) = game_data.pullable_orb_effects.pop() else {
+        return Some(game_over(
+            game_data.clone(),
+            GameOverReason::FiveOrDieFailed,
+        ));
+    };
 
-    while effects_applied < 5 {
-        match game_data.pullable_orb_effects.pop() {
-            Some(effect) => {
-                // Apply the effect
-                let mut temp_game = Game::Level {
-                    game_data: game_data.clone(),
-                };
-                game_data.pulled_orbs_effects.push(effect);
-                apply_orb_effect(effect, &mut game_data, &mut temp_game);
+    // Apply the effect
+    let mut temp_game = Game::Level {
+        game_data: game_data.clone(),
+    };
+    game_data.pulled_orbs_effects.push(effect);
+    game_data.summary.record_pull(game_data.level);
+    let points_before = game_data.points;
+    apply_orb_effect(effect, game_data, &mut temp_game);
+    game_data
+        .summary
+        .record_points(game_data.points - points_before);
+
+    // Update bomb immunity
+    if game_data.bomb_immunity_turns > 0 {
+        game_data.bomb_immunity_turns -= 1;
+    }
 
-                // Update bomb immunity
-                if game_data.bomb_immunity_turns > 0 {
-                    game_data.bomb_immunity_turns -= 1;
-                }
+    // Check win/lose conditions after each pull
+    if game_data.points >= game_data.milestone {
+        // Add FiveOrDie orbs back before returning
+        let mut game_data = game_data.clone();
+        game_data
+            .pullable_orb_effects
+            .extend(fiveordie_orbs.iter().copied());
+        Some(Game::LevelComplete { game_data })
+    } else if game_data.hp == 0 {
+        Some(game_over(
+            game_data.clone(),
+            GameOverReason::FiveOrDieFailed,
+        ))
+    } else {
+        None
+    }
+}
 
-                effects_applied += 1;
+/// Pulls the orb at the end of the bag and resolves the level.
+fn pull_last_orb(mut game_data: GameData) -> Game {
+    match game_data.pullable_orb_effects.pop() {
+        Some(effect) => {
+            game_data.pulled_orbs_effects.push(effect);
+            game_data.summary.record_pull(game_data.level);
+
+            // apply pulled effect, only FiveOrDie replaces the game state
+            let points_before = game_data.points;
+            let mut game = Game::New;
+            match apply_orb_effect(effect, &mut game_data, &mut game) {
+                OrbEffectResult::Continue => {}
+                OrbEffectResult::GameStateChanged => return game,
+            }
+            game_data
+                .summary
+                .record_points(game_data.points - points_before);
 
-                // Check win/lose conditions after each pull
-                if game_data.points >= game_data.milestone {
-                    // Add FiveOrDie orbs back before returning
-                    game_data.pullable_orb_effects.extend(fiveordie_orbs);
-                    return Game::LevelComplete { game_data };
-                } else if game_data.hp == 0 {
-                    let moonrocks_diff = calculate_moonrocks_diff(&game_data);
-                    return Game::GameOver { moonrocks_diff };
-                }
+            // update bomb immunity
+            if game_data.bomb_immunity_turns > 0 {
+                game_data.bomb_immunity_turns -= 1;
             }
-            None => {
-                let moonrocks_diff = calculate_moonrocks_diff(&game_data);
-                return Game::GameOver { moonrocks_diff };
+
+            // check if win/lose/continue
+            match (game_data.points >= game_data.milestone, game_data.hp == 0) {
+                (true, _) => Game::LevelComplete { game_data },
+                (_, true) => game_over(game_data, GameOverReason::HpDepleted),
+                _ => Game::Level { game_data },
             }
         }
+        None => game_over(game_data, GameOverReason::BagEmpty),
     }
+}
 
-    // Add the FiveOrDie orbs back to pullable effects
-    game_data.pullable_orb_effects.extend(fiveordie_orbs);
-    Game::Level { game_data }
+/// Levels in a run; completing the last one leaves only cashing out.
+pub const MAX_LEVEL: u32 = 7;
+
+/// Shop slots filled from each rarity: common, rare, cosmic.
+const SHOP_SLOTS_PER_RARITY: [usize; 3] = [3, 2, 1];
+
+/// Catalogue indices of buyable orbs, grouped like [`SHOP_SLOTS_PER_RARITY`].
+fn shop_candidates(game_data: &GameData) -> [Vec<usize>; 3] {
+    let indices = |rarity: OrbRarity| -> Vec<usize> {
+        game_data
+            .all_orbs
+            .iter()
+            .enumerate()
+            .filter(|(_, orb)| orb.rarity == rarity && orb.is_buyable())
+            .map(|(i, _)| i)
+            .collect()
+    };
+    [
+        indices(OrbRarity::Common),
+        indices(OrbRarity::Rare),
+        indices(OrbRarity::Cosmic),
+    ]
+}
+
+fn open_shop(game_data: &GameData, sale_orbs_indices: Vec<usize>) -> Game {
+    let mut game_data = GameData {
+        sale_orbs_indices,
+        ..game_data.clone()
+    };
+
+    let moonrocks_charged = game_data.level_cost(game_data.level);
+    game_data.moonrocks_spent += moonrocks_charged;
+    game_data.summary.level_mut(game_data.level).shop = Some(ShopSummary {
+        moonrocks_charged,
+        ..ShopSummary::default()
+    });
+
+    Game::Shop { game_data }
 }
 
 pub fn perform_action(game: &mut Game, action: Action) -> Result<(), ActionError> {
-    match (&game, action) {
-        (Game::New, Action::StartGame) => {
-            let mut game_data = GameData::new();
-            game_data.moonrocks_spent += GameData::LEVEL_COST_IN_MOONROCKS[0];
+    perform_action_with_rng(game, action, &mut rand::rng())
+}
+
+/// Deterministic RNG for the `step`-th action of a run started from `seed`.
+/// Recorded traces and verified runs are replayed with the same derivation.
+pub fn step_rng(seed: u64, step: u64) -> StdRng {
+    let mut bytes = [0u8; 32];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    bytes[8..16].copy_from_slice(&step.to_le_bytes());
+    StdRng::from_seed(bytes)
+}
+
+/// Where the state machine's chance comes from. Every [`Rng`] is a source;
+/// [`Script`](crate::script::Script) replaces it with a fixed pull order.
+pub trait Randomness {
+    /// Reorders the bag before pulling from its end. `pulls_so_far` counts
+    /// every pull of the run before this one.
+    fn shuffle_bag(&mut self, bag: &mut [OrbEffect], pulls_so_far: u32);
 
-            *game = Game::Level { game_data };
+    /// Picks `amount` catalogue indices out of one rarity's shop `candidates`.
+    fn pick_shop_orbs(&mut self, candidates: &[usize], amount: usize) -> Vec<usize>;
+}
+
+impl<R: Rng + ?Sized> Randomness for R {
+    fn shuffle_bag(&mut self, bag: &mut [OrbEffect], _pulls_so_far: u32) {
+        bag.shuffle(self);
+    }
+
+    fn pick_shop_orbs(&mut self, candidates: &[usize], amount: usize) -> Vec<usize> {
+        candidates.choose_multiple(self, amount).copied().collect()
+    }
+}
+
+/// Same as [`perform_action`], drawing bag shuffles and shop picks from `rng`.
+pub fn perform_action_with_rng<R: Randomness + ?Sized>(
+    game: &mut Game,
+    action: Action,
+    rng: &mut R,
+) -> Result<(), ActionError> {
+    perform_action_with_rules(game, action, &Rules::default(), rng)
+}
+
+/// Same as [`perform_action_with_rng`], starting new runs under `rules`.
+/// Every other action plays under the rules its run was started with.
+pub fn perform_action_with_rules<R: Randomness + ?Sized>(
+    game: &mut Game,
+    action: Action,
+    rules: &Rules,
+    rng: &mut R,
+) -> Result<(), ActionError> {
+    match (&game, action) {
+        (Game::New, Action::StartGame(stake)) => {
+            *game = Game::start(rules.clone(), stake);
+            Ok(())
+        }
+        (Game::New, Action::StartPractice) => {
+            *game = Game::start_practice(rules.clone());
             Ok(())
         }
         (Game::Level { game_data }, Action::PullOrb) => {
             let mut game_data = game_data.clone();
 
-            let mut rng = rand::rng();
-            game_data.pullable_orb_effects.shuffle(&mut rng);
-            match game_data.pullable_orb_effects.pop() {
-                Some(effect) => {
-                    game_data.pulled_orbs_effects.push(effect);
-
-                    // apply pulled effect
-                    match apply_orb_effect(effect, &mut game_data, game) {
-                        OrbEffectResult::Continue => {}
-                        OrbEffectResult::GameStateChanged => return Ok(()),
-                    }
-
-                    // update bomb immunity
-                    if game_data.bomb_immunity_turns > 0 {
-                        game_data.bomb_immunity_turns -= 1;
-                    }
-
-                    // check if win/lose/continue
-                    match (game_data.points >= game_data.milestone, game_data.hp == 0) {
-                        (true, _) => {
-                            *game = Game::LevelComplete { game_data };
-                            Ok(())
-                        }
-                        (_, true) => {
-                            let moonrocks_diff = calculate_moonrocks_diff(&game_data);
-                            *game = Game::GameOver { moonrocks_diff };
-                            Ok(())
-                        }
-                        _ => {
-                            *game = Game::Level { game_data };
-                            Ok(())
-                        }
-                    }
-                }
-                None => {
-                    let moonrocks_diff = calculate_moonrocks_diff(&game_data);
-                    *game = Game::GameOver { moonrocks_diff };
-                    Ok(())
-                }
-            }
+            let pulls_so_far = game_data.summary.total_pulls;
+            rng.shuffle_bag(&mut game_data.pullable_orb_effects, pulls_so_far);
+            *game = pull_last_orb(game_data);
+            Ok(())
         }
         (Game::Level { game_data }, Action::CashOut) => match game_data.points == 0 {
             true => Err(ActionError::NoPointsToCashOut),
             false => {
-                let moonrocks_diff = calculate_moonrocks_diff(game_data);
-                *game = Game::GameOver { moonrocks_diff };
+                *game = game_over(game_data.clone(), GameOverReason::CashedOut);
                 Ok(())
             }
         },
         (Game::LevelComplete { game_data }, Action::CashOut) => match game_data.points == 0 {
             true => Err(ActionError::NoPointsToCashOut),
             false => {
-                let moonrocks_diff = calculate_moonrocks_diff(game_data);
-                *game = Game::GameOver { moonrocks_diff };
+                *game = game_over(game_data.clone(), GameOverReason::CashedOut);
                 Ok(())
             }
         },
+        (Game::LevelComplete { game_data }, Action::EnterShop) if game_data.level >= MAX_LEVEL => {
+            Err(ActionError::NoNextLevel)
+        }
         (Game::LevelComplete { game_data }, Action::EnterShop) => {
-            // filter buyable orbs of each rarity
-            let common_indices: Vec<usize> = game_data
-                .all_orbs
-                .iter()
-                .enumerate()
-                .filter(|(_, orb)| orb.is_common() && orb.is_buyable())
-                .map(|(i, _)| i)
-                .collect();
-            let rare_indices: Vec<usize> = game_data
-                .all_orbs
-                .iter()
-                .enumerate()
-                .filter(|(_, orb)| orb.is_rare() && orb.is_buyable())
-                .map(|(i, _)| i)
-                .collect();
-            let cosmic_indices: Vec<usize> = game_data
-                .all_orbs
-                .iter()
-                .enumerate()
-                .filter(|(_, orb)| orb.is_cosmic() && orb.is_buyable())
-                .map(|(i, _)| i)
-                .collect();
-
             // randomly select 3 common, 2 rare, and 1 cosmic for shop
-            let mut rng = rand::rng();
             let mut sale_orbs_indices = Vec::new();
+            for (candidates, slots) in shop_candidates(game_data).iter().zip(SHOP_SLOTS_PER_RARITY)
+            {
+                sale_orbs_indices.extend(rng.pick_shop_orbs(candidates, slots));
+            }
 
-            let selected_common: Vec<usize> = common_indices
-                .choose_multiple(&mut rng, 3)
-                .cloned()
-                .collect();
-
-            let selected_rare: Vec<usize> =
-                rare_indices.choose_multiple(&mut rng, 2).cloned().collect();
-
-            let selected_cosmic: Vec<usize> = cosmic_indices
-                .choose_multiple(&mut rng, 1)
-                .cloned()
-                .collect();
-
-            sale_orbs_indices.extend(selected_common);
-            sale_orbs_indices.extend(selected_rare);
-            sale_orbs_indices.extend(selected_cosmic);
-            assert!(sale_orbs_indices.len() == 6);
-
-            let mut game_data = GameData {
-                sale_orbs_indices,
-                ..game_data.clone()
-            };
-
-            game_data.moonrocks_spent +=
-                GameData::LEVEL_COST_IN_MOONROCKS[game_data.level as usize];
-
-            *game = Game::Shop { game_data };
+            *game = open_shop(game_data, sale_orbs_indices);
             Ok(())
         }
         (Game::Shop { game_data }, Action::BuyOrb(shop_slot)) => {
             let selector_idx = shop_slot as usize;
-            let orb_idx = game_data.sale_orbs_indices[selector_idx];
+            let Some(&orb_idx) = game_data.sale_orbs_indices.get(selector_idx) else {
+                return Err(ActionError::EmptyShopSlot(shop_slot));
+            };
 
             match game_data.all_orbs[orb_idx].buyable {
                 Buyable::No => Err(ActionError::BrokenErrorNonBuyableInShop),
@@ -365,15 +688,25 @@ pub fn perform_action(game: &mut Game, action: Action) -> Result<(), ActionError
                         game_data.all_orbs[orb_idx].count += 1;
                         game_data.all_orbs[orb_idx].buyable = Buyable::Yes {
                             base_price,
-                            current_price: (current_price as f32 * 1.2).ceil() as u32,
+                            current_price: increased_price(current_price),
                         };
+                        let level = game_data.level;
+                        game_data
+                            .summary
+                            .record_purchase(level, orb_idx, current_price);
                         *game = Game::Shop { game_data };
                         Ok(())
                     }
-                    false => Err(ActionError::OrbTooExpensive),
+                    false => Err(ActionError::OrbTooExpensive {
+                        price: current_price,
+                        chips: game_data.glitch_chips,
+                    }),
                 },
             }
         }
+        (Game::Shop { game_data }, Action::GoToNextLevel) if game_data.level >= MAX_LEVEL => {
+            Err(ActionError::NoNextLevel)
+        }
         (Game::Shop { game_data }, Action::GoToNextLevel) => {
             *game = Game::Level {
                 game_data: GameData::next_level_game_data(game_data),
@@ -384,7 +717,7 @@ pub fn perform_action(game: &mut Game, action: Action) -> Result<(), ActionError
             let game_data = game_data.clone();
             match decision {
                 true => {
-                    *game = handle_five_or_die_pulls(game_data);
+                    *game = handle_five_or_die_pulls(game_data, rng);
                     Ok(())
                 }
                 false => {
@@ -393,33 +726,32 @@ pub fn perform_action(game: &mut Game, action: Action) -> Result<(), ActionError
                 }
             }
         }
-        (Game::New, _) => Err(ActionError::InvalidActionInNewGame),
-        (Game::Level { .. }, _) => Err(ActionError::InvalidActionInLevel),
-        (Game::LevelComplete { .. }, _) => Err(ActionError::InvalidActionInLevelComplete),
-        (Game::Shop { .. }, _) => Err(ActionError::InvalidActionInShop),
-        (Game::FiveOrDiePhase { game_data: _ }, _) => {
-            Err(ActionError::InvalidActionInFiveOrDiePhase)
-        }
-        (Game::GameOver { .. }, _) => Err(ActionError::GameOver),
+        (state, action) => Err(ActionError::InvalidAction {
+            state: state.kind(),
+            action: action.kind(),
+        }),
     }
 }
 
-fn calculate_moonrocks_diff(game_data: &GameData) -> i32 {
-    let mut moonrocks_diff = 0;
-    moonrocks_diff += game_data.points as i32;
-    moonrocks_diff += game_data.moonrocks_earned as i32;
-    moonrocks_diff -= game_data.moonrocks_spent as i32;
-    moonrocks_diff
+fn game_over(mut game_data: GameData, reason: GameOverReason) -> Game {
+    let settlement = game_data.settlement();
+    let moonrocks_diff = settlement.net;
+    game_data.summary.moonrocks = settlement;
+    Game::GameOver {
+        game_data,
+        reason,
+        moonrocks_diff,
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct GameData {
     pub level: u32,
     pub points: u32,
     pub milestone: u32,
     pub hp: u32,
     pub max_hp: u32,
-    pub multiplier: f32,
+    pub multiplier: u32,
     pub glitch_chips: u32,
     pub moonrocks_spent: u32,
     pub moonrocks_earned: u32,
@@ -429,11 +761,36 @@ pub struct GameData {
     pub pulled_orbs_effects: Vec<OrbEffect>,
 
     pub bomb_immunity_turns: u32,
+
+    /// Model-only statistics for the end-of-run screen; not stored on chain.
+    #[serde(default)]
+    pub summary: RunSummary,
+    /// Model-only economy settings; the contract always plays the defaults.
+    #[serde(default)]
+    pub rules: Rules,
+    /// Scales the charges and the payout; packed with the header, so it is
+    /// part of the state commitment.
+    #[serde(default)]
+    pub stake: Stake,
+    /// Practice runs are never charged and settle to zero. Packed with the
+    /// header like `stake`.
+    #[serde(default)]
+    pub practice: bool,
+}
+
+impl Default for GameData {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GameData {
-    const MILESTONES: [u32; 7] = [12, 18, 28, 44, 70, 100, 150];
-    const LEVEL_COST_IN_MOONROCKS: [u32; 7] = [10, 1, 2, 4, 6, 9, 13];
+    const MILESTONES: [u32; MAX_LEVEL as usize] = [12, 18, 28, 44, 70, 100, 150];
+    const LEVEL_COST_IN_MOONROCKS: [u32; MAX_LEVEL as usize] = [10, 1, 2, 4, 6, 9, 13];
+    /// Moonrocks a new run counts as spent before the level 0 entry cost is
+    /// charged on top. Whether the contract really charges both is not
+    /// confirmed yet, so [`Player`](wallet::Player) does not debit this part.
+    pub const OPENING_MOONROCKS_SPENT: u32 = 10;
 
     pub fn new() -> Self {
         let all_orbs = Orb::all_orbs();
@@ -448,15 +805,51 @@ impl GameData {
             milestone: Self::MILESTONES[0],
             hp: 5,
             max_hp: 5,
-            multiplier: 1.0,
+            multiplier: MULTIPLIER_SCALE,
             glitch_chips: 0,
-            moonrocks_spent: 10,
+            moonrocks_spent: Self::OPENING_MOONROCKS_SPENT,
             moonrocks_earned: 0,
             all_orbs,
             sale_orbs_indices: Vec::new(),
             pullable_orb_effects,
             pulled_orbs_effects: Vec::new(),
             bomb_immunity_turns: 0,
+            summary: RunSummary::default(),
+            rules: Rules::default(),
+            stake: Stake::default(),
+            practice: false,
+        }
+    }
+
+    /// Replaces the orb catalogue and refills the bag from it.
+    pub fn set_catalogue(&mut self, catalogue: [Orb; 21]) {
+        self.all_orbs = catalogue;
+        self.pullable_orb_effects = catalogue
+            .iter()
+            .flat_map(|orb| orb.to_orb_effects())
+            .collect();
+    }
+
+    /// Moonrocks charged to enter level 0 at `stake`.
+    pub fn entry_cost(stake: Stake) -> u32 {
+        Self::LEVEL_COST_IN_MOONROCKS[0] * stake.multiplier()
+    }
+
+    /// [`Self::OPENING_MOONROCKS_SPENT`] at this run's stake. Practice runs
+    /// are free.
+    pub fn opening_spend(&self) -> u32 {
+        match self.practice {
+            true => 0,
+            false => Self::OPENING_MOONROCKS_SPENT * self.stake.multiplier(),
+        }
+    }
+
+    /// Moonrocks charged to enter `level` at this run's stake; level 0 is the
+    /// entry cost. Practice runs are free.
+    pub fn level_cost(&self, level: u32) -> u32 {
+        match self.practice {
+            true => 0,
+            false => Self::LEVEL_COST_IN_MOONROCKS[level as usize] * self.stake.multiplier(),
         }
     }
 
@@ -476,12 +869,16 @@ impl GameData {
             moonrocks_earned: self.moonrocks_earned,
             all_orbs: self.all_orbs,
             pullable_orb_effects,
+            summary: self.summary.clone(),
+            rules: self.rules.clone(),
+            stake: self.stake,
+            practice: self.practice,
             ..new_game_data
         }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Orb {
     pub effect: OrbEffect,
     pub rarity: OrbRarity,
@@ -547,7 +944,7 @@ impl Orb {
         Self::new(OrbEffect::Health(hp), rarity, count, buyable)
     }
 
-    pub fn multiplier(mult: f32, count: u32, rarity: OrbRarity, buyable: Buyable) -> Self {
+    pub fn multiplier(mult: u32, count: u32, rarity: OrbRarity, buyable: Buyable) -> Self {
         Self::new(OrbEffect::Multiplier(mult), rarity, count, buyable)
     }
 
@@ -578,14 +975,14 @@ impl Orb {
             Self::point(7, 0, OrbRarity::Common, Buyable::buyable(8)),
             Self::moonrocks(15, 0, OrbRarity::Common, Buyable::buyable(8)),
             Self::point_rewind(0, OrbRarity::Common, Buyable::buyable(8)),
-            Self::multiplier(0.5, 0, OrbRarity::Common, Buyable::buyable(9)),
+            Self::multiplier(50, 0, OrbRarity::Common, Buyable::buyable(9)),
             Self::health(1, 1, OrbRarity::Common, Buyable::buyable(9)),
             // rare buyables
             Self::point(8, 0, OrbRarity::Rare, Buyable::buyable(11)),
             Self::point(9, 0, OrbRarity::Rare, Buyable::buyable(13)),
-            Self::multiplier(1.0, 1, OrbRarity::Rare, Buyable::buyable(14)),
+            Self::multiplier(100, 1, OrbRarity::Rare, Buyable::buyable(14)),
             Self::point_per_orb_remaining(2, 0, OrbRarity::Rare, Buyable::buyable(15)),
-            Self::multiplier(1.5, 0, OrbRarity::Rare, Buyable::buyable(16)),
+            Self::multiplier(150, 0, OrbRarity::Rare, Buyable::buyable(16)),
             // cosmic buyables
             Self::health(3, 0, OrbRarity::Cosmic, Buyable::buyable(21)),
             Self::moonrocks(40, 0, OrbRarity::Cosmic, Buyable::buyable(23)),
@@ -594,7 +991,7 @@ impl Orb {
     }
 
     pub fn to_orb_effects(&self) -> Vec<OrbEffect> {
-        vec![self.effect.clone(); self.count as usize]
+        vec![self.effect; self.count as usize]
     }
 
     pub fn is_common(&self) -> bool {
@@ -610,21 +1007,18 @@ impl Orb {
     }
 
     pub fn is_buyable(&self) -> bool {
-        match self.buyable {
-            Buyable::Yes { .. } => true,
-            _ => false,
-        }
+        matches!(self.buyable, Buyable::Yes { .. })
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum OrbRarity {
     Common,
     Rare,
     Cosmic,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum OrbEffect {
     Point(u32),
     PointPerOrbRemaining(u32),
@@ -633,13 +1027,14 @@ pub enum OrbEffect {
     Moonrocks(u32),
     Health(u32),
     Bomb(u32),
-    Multiplier(f32),
+    /// Added to the multiplier, in hundredths.
+    Multiplier(u32),
     PointRewind,
     FiveOrDie,
     BombImmunity(u32),
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Buyable {
     No,
     Yes { base_price: u32, current_price: u32 },
@@ -650,6 +1045,7 @@ impl Buyable {
         Buyable::No
     }
 
+    #[allow(clippy::self_named_constructors)]
     pub fn buyable(base_price: u32) -> Self {
         Buyable::Yes {
             base_price,
@@ -661,10 +1057,317 @@ impl Buyable {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::script::Script;
 
     #[test]
     fn test_pullable_orb_effects_count() {
         let game_data = GameData::new();
         assert_eq!(game_data.pullable_orb_effects.len(), 11);
     }
+
+    #[test]
+    fn test_multiplier_rounding_matrix() {
+        // (points, multiplier in hundredths, expected)
+        let cases = [
+            (0, 100, 0),
+            (5, 100, 5),
+            (5, 150, 7),
+            (7, 150, 10),
+            (9, 150, 13),
+            (5, 250, 12),
+            (3, 350, 10),
+            (1, 50, 0),
+            (11, 350, 38),
+            (12, 400, 48),
+            (u32::MAX, 100, u32::MAX),
+        ];
+        for (points, multiplier, expected) in cases {
+            assert_eq!(
+                apply_multiplier(points, multiplier),
+                expected,
+                "{points} points at {multiplier}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_point_effects_use_fixed_point_multiplier() {
+        let mut game_data = GameData::new();
+        game_data.multiplier = 250;
+
+        handle_point_effect(5, &mut game_data);
+        assert_eq!(game_data.points, 12);
+
+        // 11 orbs remaining at 2.5x
+        handle_point_per_orb_remaining_effect(1, &mut game_data);
+        assert_eq!(game_data.points, 12 + 27);
+
+        game_data.points = 0;
+        game_data.pulled_orbs_effects = vec![OrbEffect::Bomb(1), OrbEffect::Bomb(2)];
+        handle_point_per_bomb_pulled_effect(3, &mut game_data);
+        assert_eq!(game_data.points, 15);
+    }
+
+    #[test]
+    fn test_action_error_context() {
+        let mut game = Game::Level {
+            game_data: GameData::new(),
+        };
+        let error = perform_action(&mut game, Action::EnterShop).unwrap_err();
+        assert_eq!(
+            error,
+            ActionError::InvalidAction {
+                state: GameStateKind::Level,
+                action: ActionKind::EnterShop,
+            }
+        );
+        assert_eq!(error.to_string(), "cannot enter the shop during a level");
+        assert_eq!(
+            ActionError::EmptyShopSlot(InShopSlot::Two).to_string(),
+            "shop slot 2 is empty"
+        );
+
+        let mut game_data = GameData::new();
+        game_data.glitch_chips = 4;
+        game_data.sale_orbs_indices = vec![4, 5, 6, 13, 14, 18];
+        let mut game = Game::Shop { game_data };
+        let error = perform_action(&mut game, Action::BuyOrb(InShopSlot::One)).unwrap_err();
+        assert_eq!(error, ActionError::OrbTooExpensive { price: 5, chips: 4 });
+        assert_eq!(
+            error.to_string(),
+            "orb costs 5 glitch chips and only 4 are available"
+        );
+    }
+
+    #[test]
+    fn test_game_over_keeps_final_data() {
+        let mut game_data = GameData::new();
+        game_data.points = 9;
+        let mut game = Game::Level {
+            game_data: game_data.clone(),
+        };
+        perform_action(&mut game, Action::CashOut).unwrap();
+        let Game::GameOver {
+            game_data: final_data,
+            reason,
+            moonrocks_diff,
+        } = &game
+        else {
+            panic!("cashing out ends the game");
+        };
+        assert_eq!(*reason, GameOverReason::CashedOut);
+        // 9 points against the 10 a new GameData counts as spent
+        assert_eq!(*moonrocks_diff, -1);
+        assert_eq!(final_data.points, 9);
+        assert_eq!(final_data.summary.moonrocks.net, -1);
+
+        game_data.pullable_orb_effects.clear();
+        let mut game = Game::Level { game_data };
+        perform_action(&mut game, Action::PullOrb).unwrap();
+        assert!(matches!(
+            game,
+            Game::GameOver {
+                reason: GameOverReason::BagEmpty,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_run_summary_tracks_pulls_and_shop() {
+        let mut game_data = GameData::new();
+        game_data.pullable_orb_effects = vec![OrbEffect::Point(5), OrbEffect::Bomb(3)];
+        game_data.glitch_chips = 20;
+        let mut game = Game::Level { game_data };
+
+        perform_action(&mut game, Action::PullOrb).unwrap();
+        perform_action(&mut game, Action::PullOrb).unwrap();
+        let Game::Level { mut game_data } = game else {
+            panic!("still in the level");
+        };
+        assert_eq!(game_data.summary.total_pulls, 2);
+        assert_eq!(game_data.summary.damage_taken, 3);
+        assert_eq!(game_data.summary.best_pull, 5);
+
+        game_data.sale_orbs_indices = vec![4, 5, 6, 13, 14, 18];
+        let mut game = Game::Shop { game_data };
+        perform_action(&mut game, Action::BuyOrb(InShopSlot::One)).unwrap();
+        let Game::Shop { game_data } = game else {
+            panic!("still in the shop");
+        };
+        let shop = game_data.summary.levels[0].shop.as_ref().unwrap();
+        assert_eq!(shop.chips_spent, 5);
+        assert_eq!(shop.orbs_bought, vec![4]);
+        assert_eq!(game_data.summary.levels[0].pulls, 2);
+    }
+
+    #[test]
+    fn test_increased_price_matrix() {
+        let cases = [
+            (5, 6),
+            (6, 8),
+            (8, 10),
+            (9, 11),
+            (14, 17),
+            (21, 26),
+            (25, 30),
+        ];
+        for (price, expected) in cases {
+            assert_eq!(increased_price(price), expected, "price {price}");
+        }
+    }
+
+    fn play_in_bag_order(game: &mut Game, actions: &[Action]) {
+        let mut script = Script::in_bag_order();
+        for action in actions {
+            perform_action_with_rng(game, *action, &mut script).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_point_rewind_returns_lowest_point_orb() {
+        let game_data = GameData::builder()
+            .level(2)
+            .pull_order(vec![
+                OrbEffect::Point(7),
+                OrbEffect::Point(5),
+                OrbEffect::PointRewind,
+                OrbEffect::Bomb(1),
+            ])
+            .build();
+        let mut game = Game::Level { game_data };
+        play_in_bag_order(&mut game, &[Action::PullOrb; 3]);
+
+        let Game::Level { game_data } = &game else {
+            panic!("level");
+        };
+        assert_eq!(game_data.points, 12);
+        assert_eq!(
+            game_data.pulled_orbs_effects,
+            [OrbEffect::Point(7), OrbEffect::PointRewind]
+        );
+        assert_eq!(
+            game_data.pullable_orb_effects,
+            [OrbEffect::Bomb(1), OrbEffect::Point(5)]
+        );
+
+        // the rewound orb is the next one pulled
+        play_in_bag_order(&mut game, &[Action::PullOrb]);
+        let Game::Level { game_data } = &game else {
+            panic!("level");
+        };
+        assert_eq!(game_data.points, 17);
+        assert_eq!(game_data.pullable_orb_effects, [OrbEffect::Bomb(1)]);
+    }
+
+    #[test]
+    fn test_five_or_die_pulls() {
+        let five_or_die = |hp, pulls: Vec<OrbEffect>| {
+            let game_data = GameData::builder()
+                .hp(hp)
+                .pull_order([vec![OrbEffect::FiveOrDie], pulls].concat())
+                .build();
+            let mut game = Game::Level { game_data };
+            play_in_bag_order(
+                &mut game,
+                &[Action::PullOrb, Action::ConfirmFiveOrDie(true)],
+            );
+            game
+        };
+
+        let game = five_or_die(5, vec![OrbEffect::Point(5); 4]);
+        let Game::LevelComplete { game_data } = game else {
+            panic!("milestone met on the third pull");
+        };
+        assert_eq!(game_data.points, 15);
+        assert_eq!(game_data.pullable_orb_effects, [OrbEffect::Point(5)]);
+
+        let game = five_or_die(2, vec![OrbEffect::Bomb(1); 3]);
+        assert!(matches!(
+            game,
+            Game::GameOver {
+                reason: GameOverReason::FiveOrDieFailed,
+                ..
+            }
+        ));
+
+        let game = five_or_die(
+            5,
+            [vec![OrbEffect::Point(1); 5], vec![OrbEffect::FiveOrDie]].concat(),
+        );
+        let Game::Level { game_data } = game else {
+            panic!("five pulls without reaching the milestone");
+        };
+        assert_eq!(game_data.points, 5);
+        assert_eq!(game_data.pullable_orb_effects, [OrbEffect::FiveOrDie]);
+
+        let game = five_or_die(5, vec![OrbEffect::Point(1)]);
+        assert!(matches!(
+            game,
+            Game::GameOver {
+                reason: GameOverReason::FiveOrDieFailed,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_shop_stock_and_purchase() {
+        let game_data = GameData::builder().points(12).glitch_chips(20).build();
+        let mut game = Game::LevelComplete { game_data };
+        play_in_bag_order(
+            &mut game,
+            &[Action::EnterShop, Action::BuyOrb(InShopSlot::One)],
+        );
+
+        let Game::Shop { game_data } = &game else {
+            panic!("shop");
+        };
+        assert_eq!(game_data.sale_orbs_indices, [4, 5, 6, 13, 14, 18]);
+        assert_eq!(game_data.glitch_chips, 15);
+        assert_eq!(game_data.all_orbs[4].count, 4);
+        assert_eq!(
+            game_data.all_orbs[4].buyable,
+            Buyable::Yes {
+                base_price: 5,
+                current_price: 6,
+            }
+        );
+
+        let error = perform_action(&mut game, Action::BuyOrb(InShopSlot::Six)).unwrap_err();
+        assert_eq!(
+            error,
+            ActionError::OrbTooExpensive {
+                price: 21,
+                chips: 15
+            }
+        );
+    }
+
+    #[test]
+    fn test_final_level_and_short_shop_are_errors() {
+        let game_data = GameData::builder().level(MAX_LEVEL).points(150).build();
+        let mut game = Game::LevelComplete { game_data };
+        assert_eq!(
+            perform_action(&mut game, Action::EnterShop),
+            Err(ActionError::NoNextLevel)
+        );
+
+        let game_data = GameData::builder()
+            .glitch_chips(50)
+            .sale_orbs(vec![4])
+            .build();
+        let mut game = Game::Shop { game_data };
+        assert_eq!(
+            perform_action(&mut game, Action::BuyOrb(InShopSlot::Two)),
+            Err(ActionError::EmptyShopSlot(InShopSlot::Two))
+        );
+
+        let game_data = GameData::builder().level(MAX_LEVEL).build();
+        let mut game = Game::Shop { game_data };
+        assert_eq!(
+            perform_action(&mut game, Action::GoToNextLevel),
+            Err(ActionError::NoNextLevel)
+        );
+    }
 }
@@ -1,3 +1,36 @@
+//! JSON-RPC server for the state machine. Needs the `server` feature:
+//! `cargo run --features server -- [addr]`.
+
+use statemachine_glitchbomb::rpc::RpcServer;
+use tiny_http::{Header, Method, Response, Server};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:5050";
+
 fn main() {
-    println!("Hello, world!");
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDR.to_string());
+    let server = Server::http(&addr).expect("failed to bind rpc server");
+    println!("glitchbomb json-rpc listening on http://{addr}");
+
+    let json = Header::from_bytes("Content-Type", "application/json").unwrap();
+    let mut rpc = RpcServer::new();
+
+    for mut request in server.incoming_requests() {
+        if *request.method() != Method::Post {
+            let _ = request.respond(Response::empty(405));
+            continue;
+        }
+
+        let mut body = String::new();
+        if request.as_reader().read_to_string(&mut body).is_err() {
+            let _ = request.respond(Response::empty(400));
+            continue;
+        }
+
+        let _ = match rpc.handle(&body) {
+            Some(reply) => request.respond(Response::from_string(reply).with_header(json.clone())),
+            None => request.respond(Response::empty(204)),
+        };
+    }
 }
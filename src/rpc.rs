@@ -0,0 +1,344 @@
+//! JSON-RPC 2.0 facade over the state machine.
+//!
+//! Method names and parameters mirror the glitchbomb contract systems, so a
+//! frontend can talk to this backend or to the chain by swapping the endpoint.
+//! Every method takes the `game_id` first; params may be given positionally
+//! (like contract calldata) or by name.
+//!
+//! | method                | params                          |
+//! |-----------------------|---------------------------------|
+//! | `start_game`          | `game_id`, `stake` (1, 5 or 25) |
+//! | `start_practice`      | `game_id`                       |
+//! | `pull_orb`            | `game_id`                       |
+//! | `cash_out`            | `game_id`                       |
+//! | `enter_shop`          | `game_id`                       |
+//! | `buy_orb`             | `game_id`, `slot` (0..=5)       |
+//! | `confirm_five_or_die` | `game_id`, `confirmed`          |
+//! | `next_level`          | `game_id`                       |
+//!
+//! A successful call returns the game state after the action. `stake` is
+//! optional and defaults to 1.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{Action, ActionError, Game, InShopSlot, Stake, perform_action};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// Server-defined code for actions the state machine refused.
+pub const ACTION_REJECTED: i64 = 1;
+/// Server-defined code for calls against a game id that was never started.
+pub const UNKNOWN_GAME: i64 = 2;
+
+#[derive(Debug, Serialize)]
+pub struct Response {
+    pub jsonrpc: &'static str,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    pub id: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Result(Value),
+    Error(RpcError),
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<ActionError> for RpcError {
+    fn from(error: ActionError) -> Self {
+        RpcError {
+            code: ACTION_REJECTED,
+            message: error.to_string(),
+            data: serde_json::to_value(error).ok(),
+        }
+    }
+}
+
+/// Holds every game served over RPC, keyed by the caller's game id.
+#[derive(Default)]
+pub struct RpcServer {
+    games: HashMap<u64, Game>,
+}
+
+impl RpcServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn game(&self, game_id: u64) -> Option<&Game> {
+        self.games.get(&game_id)
+    }
+
+    /// Handles a raw request body, single or batch. Returns `None` when there
+    /// is nothing to send back (notifications only).
+    pub fn handle(&mut self, body: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(body) {
+            Ok(request) => request,
+            Err(_) => {
+                let response =
+                    error_response(Value::Null, RpcError::new(PARSE_ERROR, "parse error"));
+                return serde_json::to_string(&response).ok();
+            }
+        };
+
+        match request {
+            Value::Array(batch) if batch.is_empty() => {
+                let response =
+                    error_response(Value::Null, RpcError::new(INVALID_REQUEST, "empty batch"));
+                serde_json::to_string(&response).ok()
+            }
+            Value::Array(batch) => {
+                let responses: Vec<Response> = batch
+                    .into_iter()
+                    .filter_map(|request| self.handle_value(request))
+                    .collect();
+                match responses.is_empty() {
+                    true => None,
+                    false => serde_json::to_string(&responses).ok(),
+                }
+            }
+            request => self
+                .handle_value(request)
+                .and_then(|response| serde_json::to_string(&response).ok()),
+        }
+    }
+
+    fn handle_value(&mut self, request: Value) -> Option<Response> {
+        let Value::Object(mut request) = request else {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(INVALID_REQUEST, "request must be an object"),
+            ));
+        };
+
+        // a request without an id is a notification and gets no response
+        let id = request.remove("id");
+        let is_notification = id.is_none();
+        let id = id.unwrap_or(Value::Null);
+
+        if request.get("jsonrpc") != Some(&Value::from("2.0")) {
+            return Some(error_response(
+                id,
+                RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""),
+            ));
+        }
+        let Some(Value::String(method)) = request.remove("method") else {
+            return Some(error_response(
+                id,
+                RpcError::new(INVALID_REQUEST, "method must be a string"),
+            ));
+        };
+        let params = request.remove("params").unwrap_or(Value::Null);
+
+        let outcome = match self.call(&method, &params) {
+            Ok(result) => Outcome::Result(result),
+            Err(error) => Outcome::Error(error),
+        };
+        match is_notification {
+            true => None,
+            false => Some(Response {
+                jsonrpc: "2.0",
+                outcome,
+                id,
+            }),
+        }
+    }
+
+    fn call(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        let action = match method {
+            "start_game" => Action::StartGame(stake(optional_param(params, 1, "stake")?)?),
+            "start_practice" => Action::StartPractice,
+            "pull_orb" => Action::PullOrb,
+            "cash_out" => Action::CashOut,
+            "enter_shop" => Action::EnterShop,
+            "buy_orb" => Action::BuyOrb(shop_slot(param(params, 1, "slot")?)?),
+            "confirm_five_or_die" => Action::ConfirmFiveOrDie(param(params, 1, "confirmed")?),
+            "next_level" => Action::GoToNextLevel,
+            _ => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("method not found: {method}"),
+                ));
+            }
+        };
+        let game_id: u64 = param(params, 0, "game_id")?;
+
+        let game = match action {
+            Action::StartGame(_) | Action::StartPractice => {
+                self.games.entry(game_id).or_insert(Game::New)
+            }
+            _ => self.games.get_mut(&game_id).ok_or_else(|| {
+                RpcError::new(UNKNOWN_GAME, format!("unknown game id: {game_id}"))
+            })?,
+        };
+        perform_action(game, action)?;
+
+        serde_json::to_value(&*game)
+            .map_err(|error| RpcError::new(INTERNAL_ERROR, error.to_string()))
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Response {
+    Response {
+        jsonrpc: "2.0",
+        outcome: Outcome::Error(error),
+        id,
+    }
+}
+
+fn param<T: DeserializeOwned>(params: &Value, position: usize, name: &str) -> Result<T, RpcError> {
+    optional_param(params, position, name)?
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("missing param: {name}")))
+}
+
+fn optional_param<T: DeserializeOwned>(
+    params: &Value,
+    position: usize,
+    name: &str,
+) -> Result<Option<T>, RpcError> {
+    let value = match params {
+        Value::Array(values) => values.get(position),
+        Value::Object(values) => values.get(name),
+        _ => None,
+    };
+    value
+        .map(|value| {
+            serde_json::from_value(value.clone()).map_err(|error| {
+                RpcError::new(INVALID_PARAMS, format!("invalid param {name}: {error}"))
+            })
+        })
+        .transpose()
+}
+
+fn stake(multiplier: Option<u32>) -> Result<Stake, RpcError> {
+    match multiplier {
+        None => Ok(Stake::default()),
+        Some(multiplier) => Stake::from_multiplier(multiplier).ok_or_else(|| {
+            RpcError::new(
+                INVALID_PARAMS,
+                format!("invalid param stake: {multiplier} is not a stake tier"),
+            )
+        }),
+    }
+}
+
+fn shop_slot(index: u8) -> Result<InShopSlot, RpcError> {
+    match index {
+        0 => Ok(InShopSlot::One),
+        1 => Ok(InShopSlot::Two),
+        2 => Ok(InShopSlot::Three),
+        3 => Ok(InShopSlot::Four),
+        4 => Ok(InShopSlot::Five),
+        5 => Ok(InShopSlot::Six),
+        _ => Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("invalid param slot: {index} is not a shop slot"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(server: &mut RpcServer, body: &str) -> Value {
+        serde_json::from_str(&server.handle(body).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_start_game_then_pull_orb() {
+        let mut server = RpcServer::new();
+
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc":"2.0","method":"start_game","params":{"game_id":7},"id":1}"#,
+        );
+        assert_eq!(response["id"], 1);
+        assert!(response["result"]["Level"].is_object());
+
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc":"2.0","method":"pull_orb","params":[7],"id":2}"#,
+        );
+        assert!(response.get("result").is_some());
+
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc":"2.0","method":"start_game","params":[8, 25],"id":3}"#,
+        );
+        assert_eq!(
+            response["result"]["Level"]["game_data"]["moonrocks_spent"],
+            500
+        );
+
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc":"2.0","method":"start_game","params":[9, 2],"id":4}"#,
+        );
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut server = RpcServer::new();
+
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc":"2.0","method":"pull_orb","params":[1],"id":1}"#,
+        );
+        assert_eq!(response["error"]["code"], UNKNOWN_GAME);
+
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc":"2.0","method":"launch","params":[1],"id":2}"#,
+        );
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        call(
+            &mut server,
+            r#"{"jsonrpc":"2.0","method":"start_game","params":[1],"id":3}"#,
+        );
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc":"2.0","method":"cash_out","params":[1],"id":4}"#,
+        );
+        assert_eq!(response["error"]["code"], ACTION_REJECTED);
+        assert_eq!(response["error"]["data"], "NoPointsToCashOut");
+        assert_eq!(
+            response["error"]["message"],
+            "there are no points to cash out"
+        );
+
+        let response = call(&mut server, "{");
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+        let notification = r#"{"jsonrpc":"2.0","method":"pull_orb","params":[1]}"#;
+        assert!(server.handle(notification).is_none());
+    }
+}
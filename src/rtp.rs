@@ -0,0 +1,314 @@
+//! Return-to-player estimates for a catalogue and rule configuration.
+//!
+//! Games are simulated to the end under a [`Strategy`] and the resulting
+//! `moonrocks_diff` averaged. RTP is moonrocks paid out per moonrock spent;
+//! a ruleset whose best reference strategy returns more than it costs is a
+//! positive-expectation exploit. Picking the best of many noisy estimates
+//! favours lucky ones, so the pick is scored again on games it was not chosen
+//! on, and only a confidence interval above break-even there counts.
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::rules::Rules;
+use crate::{
+    Action, Buyable, Game, GameData, InShopSlot, MAX_LEVEL, Orb, OrbEffect, Stake,
+    perform_action_with_rng,
+};
+
+/// z-score of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+/// Everything an economy change can tune: the orb catalogue and the rules.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ruleset {
+    pub catalogue: [Orb; 21],
+    pub rules: Rules,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Ruleset {
+            catalogue: Orb::all_orbs(),
+            rules: Rules::default(),
+        }
+    }
+}
+
+impl Ruleset {
+    /// A freshly started run with this catalogue in the bag.
+    pub fn start(&self) -> Game {
+        let mut game = Game::start(self.rules.clone(), Stake::default());
+        if let Game::Level { game_data } = &mut game {
+            game_data.set_catalogue(self.catalogue);
+        }
+        game
+    }
+}
+
+/// Picks the next action for a game that is not over.
+pub trait Strategy {
+    fn choose(&mut self, game: &Game) -> Action;
+}
+
+/// Reference play: pull until hp runs low, push on to `target_level` and
+/// spend chips on the priciest affordable non-bomb orb.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdStrategy {
+    /// Cash out mid-level once hp is at or below this.
+    pub cash_out_hp: u32,
+    /// Cash out on completing this level instead of entering the shop.
+    pub target_level: u32,
+    pub five_or_die: bool,
+}
+
+impl ThresholdStrategy {
+    /// Pulls through level 1, cashes out on completing it and never takes on
+    /// FiveOrDie: the shortest complete run, handy for driving tests.
+    pub const FIRST_LEVEL: ThresholdStrategy = ThresholdStrategy {
+        cash_out_hp: 0,
+        target_level: 1,
+        five_or_die: false,
+    };
+
+    /// Every combination of reasonable thresholds, used to approximate the
+    /// best strategy a player could find.
+    pub fn grid() -> Vec<ThresholdStrategy> {
+        let mut grid = Vec::new();
+        for cash_out_hp in 0..=3 {
+            for target_level in 1..=MAX_LEVEL {
+                for five_or_die in [false, true] {
+                    grid.push(ThresholdStrategy {
+                        cash_out_hp,
+                        target_level,
+                        five_or_die,
+                    });
+                }
+            }
+        }
+        grid
+    }
+
+    fn purchase(game_data: &GameData) -> Option<InShopSlot> {
+        game_data
+            .sale_orbs_indices
+            .iter()
+            .zip(InShopSlot::ALL)
+            .filter_map(|(&index, slot)| {
+                let orb = &game_data.all_orbs[index];
+                match orb.buyable {
+                    Buyable::Yes { current_price, .. }
+                        if current_price < game_data.glitch_chips
+                            && !matches!(orb.effect, OrbEffect::Bomb(_)) =>
+                    {
+                        Some((current_price, slot))
+                    }
+                    _ => None,
+                }
+            })
+            .max_by_key(|(price, _)| *price)
+            .map(|(_, slot)| slot)
+    }
+}
+
+impl Strategy for ThresholdStrategy {
+    fn choose(&mut self, game: &Game) -> Action {
+        match game {
+            Game::New | Game::GameOver { .. } => Action::StartGame(Stake::X1),
+            Game::Level { game_data } => {
+                match game_data.points > 0 && game_data.hp <= self.cash_out_hp {
+                    true => Action::CashOut,
+                    false => Action::PullOrb,
+                }
+            }
+            Game::LevelComplete { game_data } => {
+                let last_level = self.target_level.min(MAX_LEVEL);
+                match game_data.level < last_level {
+                    true => Action::EnterShop,
+                    false => Action::CashOut,
+                }
+            }
+            Game::FiveOrDiePhase { .. } => Action::ConfirmFiveOrDie(self.five_or_die),
+            Game::Shop { game_data } => match Self::purchase(game_data) {
+                Some(slot) => Action::BuyOrb(slot),
+                None => Action::GoToNextLevel,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct RtpEstimate {
+    pub games: u32,
+    pub mean_diff: f64,
+    /// 95% confidence interval for `mean_diff`.
+    pub confidence_interval: (f64, f64),
+    pub mean_cost: f64,
+    pub mean_income: f64,
+    /// Mean income over mean cost; above `1.0` the player wins on average.
+    pub rtp: f64,
+}
+
+impl RtpEstimate {
+    pub fn exceeds_full_return(&self) -> bool {
+        self.rtp > 1.0
+    }
+
+    /// The whole confidence interval lies above break-even.
+    pub fn is_significant_exploit(&self) -> bool {
+        self.confidence_interval.0 > 0.0
+    }
+}
+
+/// Plays `games` runs of `ruleset` under `strategy`.
+///
+/// # Panics
+///
+/// If the strategy chooses an action the state machine rejects.
+pub fn estimate<S: Strategy + ?Sized>(
+    ruleset: &Ruleset,
+    strategy: &mut S,
+    games: u32,
+    seed: u64,
+) -> RtpEstimate {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut diffs = Vec::with_capacity(games as usize);
+    let (mut cost, mut income) = (0u64, 0u64);
+
+    for _ in 0..games {
+        let mut game = ruleset.start();
+        while !matches!(game, Game::GameOver { .. }) {
+            let action = strategy.choose(&game);
+            perform_action_with_rng(&mut game, action, &mut rng)
+                .unwrap_or_else(|error| panic!("strategy chose {action:?}: {error}"));
+        }
+        let Game::GameOver {
+            game_data,
+            moonrocks_diff,
+            ..
+        } = game
+        else {
+            unreachable!("loop runs until game over");
+        };
+        diffs.push(moonrocks_diff as f64);
+        cost += game_data.summary.moonrocks.total_cost() as u64;
+        income += game_data.summary.moonrocks.total_income() as u64;
+    }
+
+    let n = games.max(1) as f64;
+    let mean_diff = diffs.iter().sum::<f64>() / n;
+    let variance = diffs
+        .iter()
+        .map(|diff| (diff - mean_diff).powi(2))
+        .sum::<f64>()
+        / (n - 1.0).max(1.0);
+    let margin = Z_95 * (variance / n).sqrt();
+    let (mean_cost, mean_income) = (cost as f64 / n, income as f64 / n);
+
+    RtpEstimate {
+        games,
+        mean_diff,
+        confidence_interval: (mean_diff - margin, mean_diff + margin),
+        mean_cost,
+        mean_income,
+        rtp: match mean_cost > 0.0 {
+            true => mean_income / mean_cost,
+            false => 0.0,
+        },
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RtpReport {
+    /// One estimate per strategy in [`ThresholdStrategy::grid`], best first.
+    pub estimates: Vec<(ThresholdStrategy, RtpEstimate)>,
+    /// The best strategy replayed on a held-out seed.
+    pub holdout: RtpEstimate,
+}
+
+impl RtpReport {
+    pub fn best(&self) -> &(ThresholdStrategy, RtpEstimate) {
+        &self.estimates[0]
+    }
+
+    /// The best strategy wins significantly on games it was not picked on.
+    pub fn is_exploitable(&self) -> bool {
+        self.holdout.is_significant_exploit()
+    }
+}
+
+/// Estimates RTP for every reference strategy, `games` runs each, then
+/// replays the best one on `games` runs from a held-out seed.
+pub fn analyze(ruleset: &Ruleset, games: u32, seed: u64) -> RtpReport {
+    let mut estimates: Vec<_> = ThresholdStrategy::grid()
+        .into_iter()
+        .map(|mut strategy| (strategy, estimate(ruleset, &mut strategy, games, seed)))
+        .collect();
+    estimates.sort_by(|(_, a), (_, b)| b.mean_diff.total_cmp(&a.mean_diff));
+    let mut best = estimates[0].0;
+    let holdout = estimate(ruleset, &mut best, games, !seed);
+    RtpReport { estimates, holdout }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::PayoutPolicy;
+
+    #[test]
+    fn test_generous_payout_is_flagged() {
+        let mut strategy = ThresholdStrategy {
+            cash_out_hp: 2,
+            target_level: 1,
+            five_or_die: false,
+        };
+        let with_rate = |rate| Ruleset {
+            rules: Rules {
+                payout: PayoutPolicy {
+                    rate,
+                    ..PayoutPolicy::default()
+                },
+            },
+            ..Ruleset::default()
+        };
+
+        let stingy = estimate(&with_rate(10), &mut strategy, 300, 1);
+        assert!(!stingy.exceeds_full_return());
+        assert!(stingy.confidence_interval.0 <= stingy.mean_diff);
+
+        let generous = estimate(&with_rate(1000), &mut strategy, 300, 1);
+        assert!(generous.exceeds_full_return());
+        assert!(generous.is_significant_exploit());
+    }
+
+    #[test]
+    fn test_report_flags_only_significant_holdout() {
+        let with_rate = |rate| Ruleset {
+            rules: Rules {
+                payout: PayoutPolicy {
+                    rate,
+                    ..PayoutPolicy::default()
+                },
+            },
+            ..Ruleset::default()
+        };
+
+        let generous = analyze(&with_rate(1000), 40, 2);
+        assert!(generous.is_exploitable());
+        assert_eq!(generous.holdout.games, 40);
+
+        let stingy = analyze(&with_rate(10), 40, 2);
+        assert!(!stingy.is_exploitable());
+
+        // a best estimate above break-even is not enough on its own
+        let lucky = RtpReport {
+            holdout: RtpEstimate {
+                confidence_interval: (-1.0, 3.0),
+                ..stingy.holdout
+            },
+            ..stingy
+        };
+        assert!(!lucky.is_exploitable());
+    }
+}
@@ -0,0 +1,108 @@
+//! Tunable economy settings a run is played under.
+//!
+//! [`Rules::default`] reproduces the contract: points convert 1:1 into
+//! moonrocks with no bonuses.
+//!
+//! A run's rules are not part of its felt packing or state commitment, so
+//! anything that replays a run (sessions, committed runs, verification) must
+//! be handed the same rules the run was started under.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{MULTIPLIER_SCALE, apply_multiplier};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+    pub payout: PayoutPolicy,
+}
+
+/// How points held at game over are converted into moonrocks. Rates and
+/// multipliers are fixed-point hundredths, like [`MULTIPLIER_SCALE`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PayoutPolicy {
+    /// Moonrocks per point.
+    pub rate: u32,
+    /// Scales the payout by the level the run ended on; `level_multipliers[0]`
+    /// applies to level 1. Levels past the end are paid at 1x.
+    pub level_multipliers: Vec<u32>,
+    /// Diminishing returns: points past a tier's `above` threshold are paid at
+    /// that tier's rate instead.
+    pub tiers: Vec<PayoutTier>,
+    /// Paid for every milestone reached during the run.
+    pub milestone_bonus: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PayoutTier {
+    pub above: u32,
+    pub rate: u32,
+}
+
+impl Default for PayoutPolicy {
+    fn default() -> Self {
+        PayoutPolicy {
+            rate: MULTIPLIER_SCALE,
+            level_multipliers: Vec::new(),
+            tiers: Vec::new(),
+            milestone_bonus: 0,
+        }
+    }
+}
+
+impl PayoutPolicy {
+    /// Moonrocks paid for `points` held at the end of `level`, rounded down.
+    pub fn convert(&self, points: u32, level: u32) -> u32 {
+        let mut tiers = self.tiers.clone();
+        tiers.sort_by_key(|tier| tier.above);
+
+        let mut rate = self.rate;
+        let mut from = 0;
+        let mut scaled: u64 = 0;
+        for tier in tiers.iter().filter(|tier| tier.above < points) {
+            scaled += (tier.above - from) as u64 * rate as u64;
+            from = tier.above;
+            rate = tier.rate;
+        }
+        scaled += (points - from) as u64 * rate as u64;
+
+        let level_multiplier = level
+            .checked_sub(1)
+            .and_then(|index| self.level_multipliers.get(index as usize))
+            .copied()
+            .unwrap_or(MULTIPLIER_SCALE);
+        let moonrocks = (scaled / MULTIPLIER_SCALE as u64).min(u32::MAX as u64) as u32;
+        apply_multiplier(moonrocks, level_multiplier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let policy = PayoutPolicy::default();
+        assert_eq!(policy.convert(37, 3), 37);
+
+        let policy = PayoutPolicy {
+            rate: 150,
+            level_multipliers: vec![100, 200],
+            tiers: vec![
+                PayoutTier {
+                    above: 40,
+                    rate: 25,
+                },
+                PayoutTier {
+                    above: 20,
+                    rate: 50,
+                },
+            ],
+            milestone_bonus: 0,
+        };
+        assert_eq!(policy.convert(10, 1), 15);
+        // 20 * 1.5 + 20 * 0.5 + 20 * 0.25
+        assert_eq!(policy.convert(60, 1), 45);
+        assert_eq!(policy.convert(60, 2), 90);
+        assert_eq!(policy.convert(60, 7), 45);
+    }
+}
@@ -0,0 +1,145 @@
+//! A [`Randomness`] source that plays a fixed pull order and shop stock.
+//!
+//! `pulls` lists the orbs of a whole run in the order they should come out of
+//! the bag. Before every shuffle the scripted orbs not yet pulled are moved to
+//! the end of the bag, in order, so pulls from the end follow the script. Once
+//! the script runs out, or names an orb that is not in the bag, the rest of
+//! the bag is left shuffled by the fallback RNG.
+//!
+//! `shop_orbs` lists catalogue indices six per shop visited, in slot order.
+//! Indices that are not buyable candidates, and slots left once the list runs
+//! out, are picked by the fallback RNG.
+//!
+//! [`Script::in_bag_order`] has no fallback: the bag is never reordered and
+//! shops take the first candidates of each rarity.
+
+use std::collections::VecDeque;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::{OrbEffect, Randomness};
+
+#[derive(Clone, Debug)]
+pub struct Script {
+    pulls: Vec<OrbEffect>,
+    shop_orbs: VecDeque<usize>,
+    fallback: Option<StdRng>,
+}
+
+impl Script {
+    pub fn new(pulls: Vec<OrbEffect>) -> Self {
+        Script {
+            pulls,
+            shop_orbs: VecDeque::new(),
+            fallback: Some(StdRng::seed_from_u64(0)),
+        }
+    }
+
+    /// Pulls straight from the end of the bag as it was built, for tests that
+    /// arrange the bag themselves.
+    pub fn in_bag_order() -> Self {
+        Script {
+            pulls: Vec::new(),
+            shop_orbs: VecDeque::new(),
+            fallback: None,
+        }
+    }
+
+    pub fn with_shop_orbs(mut self, shop_orbs: Vec<usize>) -> Self {
+        self.shop_orbs = shop_orbs.into();
+        self
+    }
+
+    pub fn with_fallback_seed(mut self, seed: u64) -> Self {
+        self.fallback = Some(StdRng::seed_from_u64(seed));
+        self
+    }
+}
+
+impl Randomness for Script {
+    fn shuffle_bag(&mut self, bag: &mut [OrbEffect], pulls_so_far: u32) {
+        if let Some(fallback) = &mut self.fallback {
+            fallback.shuffle_bag(bag, pulls_so_far);
+        }
+
+        let mut end = bag.len();
+        for effect in self.pulls.iter().skip(pulls_so_far as usize) {
+            let Some(index) = bag[..end].iter().rposition(|orb| orb == effect) else {
+                break;
+            };
+            bag.swap(index, end - 1);
+            end -= 1;
+        }
+    }
+
+    fn pick_shop_orbs(&mut self, candidates: &[usize], amount: usize) -> Vec<usize> {
+        let mut picks = Vec::with_capacity(amount);
+        for _ in 0..amount {
+            match self.shop_orbs.pop_front() {
+                Some(index) if candidates.contains(&index) && !picks.contains(&index) => {
+                    picks.push(index)
+                }
+                _ => {}
+            }
+        }
+
+        let rest: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|index| !picks.contains(index))
+            .collect();
+        let missing = amount.saturating_sub(picks.len());
+        match &mut self.fallback {
+            Some(fallback) => picks.extend(fallback.pick_shop_orbs(&rest, missing)),
+            None => picks.extend(rest.into_iter().take(missing)),
+        }
+        picks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, Game, perform_action_with_rng};
+
+    #[test]
+    fn test_script_sets_pull_order() {
+        let pulls = vec![
+            OrbEffect::Point(5),
+            OrbEffect::Bomb(2),
+            OrbEffect::Multiplier(100),
+            OrbEffect::Point(5),
+        ];
+        let mut script = Script::new(pulls.clone()).with_fallback_seed(8);
+        let mut game = Game::New;
+        perform_action_with_rng(&mut game, Action::StartPractice, &mut script).unwrap();
+        for _ in 0..pulls.len() {
+            perform_action_with_rng(&mut game, Action::PullOrb, &mut script).unwrap();
+        }
+
+        let Game::LevelComplete { game_data } = game else {
+            panic!("milestone met on the last pull");
+        };
+        assert_eq!(game_data.pulled_orbs_effects, pulls);
+        assert_eq!(game_data.points, 15);
+        assert_eq!(game_data.hp, 3);
+    }
+
+    #[test]
+    fn test_script_stocks_shop() {
+        let mut script = Script::new(Vec::new()).with_shop_orbs(vec![12, 4, 0, 15, 13, 19]);
+        let mut game_data = crate::GameData::new();
+        game_data.points = game_data.milestone;
+        let mut game = Game::LevelComplete { game_data };
+        perform_action_with_rng(&mut game, Action::EnterShop, &mut script).unwrap();
+
+        let Game::Shop { game_data } = game else {
+            panic!("shop");
+        };
+        // 0 is a bomb and not for sale, so the fallback fills its slot
+        assert_eq!(game_data.sale_orbs_indices[..2], [12, 4]);
+        assert_eq!(game_data.sale_orbs_indices[3..], [15, 13, 19]);
+        assert!(![0, 12, 4].contains(&game_data.sale_orbs_indices[2]));
+    }
+}
@@ -0,0 +1,211 @@
+//! Game sessions: a [`Game`] with identity and run metadata. Services persist
+//! them whole through [`crate::store::GameStore`].
+//!
+//! A session's randomness comes from its seed, the `i`-th action drawing from
+//! [`step_rng`]`(seed, i)`, so its action log can be checked with
+//! [`crate::verify::verify_run`], or
+//! [`crate::verify::verify_run_with_rules`] for a session with tuned rules.
+//!
+//! Clients that may retry or race (two tabs, resent requests) should go
+//! through [`perform_action_at`] with the nonce they last saw.
+//!
+//! With the `dev-tools` feature a session also keeps recent snapshots so
+//! playtesters can undo, redo and jump between turns. Because randomness is
+//! tied to the turn, redoing a different action from the same turn sees the
+//! same bag order.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "dev-tools")]
+use crate::history::History;
+use crate::rules::Rules;
+use crate::store::GameId;
+use crate::{Action, ActionError, Game, RULESET_VERSION, perform_action_with_rules, step_rng};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameSession {
+    pub id: GameId,
+    pub player: String,
+    pub seed: u64,
+    pub ruleset_version: u32,
+    /// Rules the session's runs are started under.
+    #[serde(default)]
+    pub rules: Rules,
+    /// Seconds since the unix epoch.
+    pub created_at: u64,
+    game: Game,
+    actions: Vec<Action>,
+    nonce: u64,
+    #[cfg(feature = "dev-tools")]
+    #[serde(skip)]
+    history: History,
+}
+
+impl GameSession {
+    pub fn new(id: GameId, player: impl Into<String>, seed: u64) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+
+        GameSession {
+            id,
+            player: player.into(),
+            seed,
+            ruleset_version: RULESET_VERSION,
+            rules: Rules::default(),
+            created_at,
+            game: Game::New,
+            actions: Vec::new(),
+            nonce: 0,
+            #[cfg(feature = "dev-tools")]
+            history: History::default(),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Actions applied to reach the current state.
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Number of actions applied to reach the current state.
+    pub fn turn(&self) -> u64 {
+        self.actions.len() as u64
+    }
+
+    /// Increases with every change to the session and never goes back, so a
+    /// client can tell which state an action was built against.
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    pub fn perform_action(&mut self, action: Action) -> Result<(), ActionError> {
+        #[cfg(feature = "dev-tools")]
+        self.history.start(&self.game);
+
+        let mut rng = step_rng(self.seed, self.turn());
+        perform_action_with_rules(&mut self.game, action, &self.rules, &mut rng)?;
+        self.actions.push(action);
+        self.nonce += 1;
+
+        #[cfg(feature = "dev-tools")]
+        self.history.record(&self.game);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "dev-tools")]
+impl GameSession {
+    /// Steps back one turn. Returns `false` if no earlier snapshot is kept.
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo(&mut self.actions) {
+            Some(game) => {
+                self.game = game;
+                self.nonce += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-applies the last undone action. Returns `false` if there is none.
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo(&mut self.actions) {
+            Some(game) => {
+                self.game = game;
+                self.nonce += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves to `turn` through undo or redo. Returns `false`, leaving the
+    /// session untouched, if that turn is out of the kept history.
+    pub fn jump_to(&mut self, turn: u64) -> bool {
+        let earliest = self.turn() - self.history.undo_depth() as u64;
+        let latest = self.turn() + self.history.redo_depth() as u64;
+        if turn < earliest || turn > latest {
+            return false;
+        }
+
+        while self.turn() > turn {
+            self.undo();
+        }
+        while self.turn() < turn {
+            self.redo();
+        }
+        true
+    }
+}
+
+/// Performs `action` only if the session is still at `expected_nonce`.
+///
+/// An action built against an older state fails with
+/// [`ActionError::StaleState`] instead of being applied on top of whatever
+/// happened since.
+pub fn perform_action_at(
+    session: &mut GameSession,
+    expected_nonce: u64,
+    action: Action,
+) -> Result<(), ActionError> {
+    if session.nonce != expected_nonce {
+        return Err(ActionError::StaleState {
+            expected: expected_nonce,
+            current: session.nonce,
+        });
+    }
+    session.perform_action(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Stake, perform_action_with_rng};
+
+    #[test]
+    fn test_session_replays_from_its_seed() {
+        let mut session = GameSession::new(9, "alice", 21);
+        session
+            .perform_action(Action::StartGame(Stake::X1))
+            .unwrap();
+        assert!(session.perform_action(Action::CashOut).is_err());
+        session.perform_action(Action::PullOrb).unwrap();
+
+        assert_eq!(session.turn(), 2);
+        assert_eq!(session.nonce(), 2);
+        assert_eq!(session.ruleset_version, RULESET_VERSION);
+
+        let mut game = Game::New;
+        for (step, action) in session.actions().iter().enumerate() {
+            perform_action_with_rng(&mut game, *action, &mut step_rng(session.seed, step as u64))
+                .unwrap();
+        }
+        assert_eq!(&game, session.game());
+    }
+
+    #[test]
+    fn test_stale_action_is_rejected() {
+        let mut session = GameSession::new(1, "bob", 3);
+        perform_action_at(&mut session, 0, Action::StartGame(Stake::X1)).unwrap();
+
+        // two tabs both saw nonce 1 and sent a pull
+        perform_action_at(&mut session, 1, Action::PullOrb).unwrap();
+        let after_first_pull = session.game().clone();
+        assert_eq!(
+            perform_action_at(&mut session, 1, Action::PullOrb),
+            Err(ActionError::StaleState {
+                expected: 1,
+                current: 2
+            })
+        );
+        assert_eq!(session.game(), &after_first_pull);
+        assert_eq!(session.nonce(), 2);
+    }
+}
@@ -0,0 +1,257 @@
+//! Itemized moonrock settlement of a run.
+//!
+//! Every moonrock in a game's `moonrocks_diff` is accounted for here: the
+//! opening spend, the entry cost, the charge for each shop entered, points
+//! converted into
+//! moonrocks under the run's [`PayoutPolicy`](crate::rules::PayoutPolicy),
+//! milestone bonuses and moonrocks won from orbs, each scaled by the stake.
+//! Practice runs settle every item to zero.
+//!
+//! Costs always add up to the stored `moonrocks_spent`: the opening spend and
+//! entry cost are known amounts, shop charges come from the run summary, and
+//! whatever the stored spend holds besides them is reported as `unexplained`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{GameData, Stake};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settlement {
+    /// [`GameData::OPENING_MOONROCKS_SPENT`] at the run's stake.
+    #[serde(default)]
+    pub opening_spend: u32,
+    /// Charged to enter level 0.
+    pub entry_cost: u32,
+    /// Charged on entering the shop after completing each level.
+    pub level_charges: Vec<LevelCharge>,
+    /// Moonrocks paid for the points held at the end.
+    pub points_converted: u32,
+    #[serde(default)]
+    pub milestone_bonus: u32,
+    /// Won from `Moonrocks` orbs.
+    pub moonrocks_income: u32,
+    /// Stored spend none of the items above account for. Zero for any run
+    /// played from the start; states built mid-run can hold some, and a
+    /// negative amount means less was stored than the known costs.
+    #[serde(default)]
+    pub unexplained: i32,
+    pub net: i32,
+    #[serde(default)]
+    pub stake: Stake,
+    #[serde(default)]
+    pub practice: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelCharge {
+    pub level: u32,
+    pub cost: u32,
+}
+
+impl Settlement {
+    /// Known costs plus the unexplained remainder, which is the stored
+    /// `moonrocks_spent`.
+    pub fn total_cost(&self) -> u32 {
+        (self.known_cost() as i32 + self.unexplained) as u32
+    }
+
+    pub fn known_cost(&self) -> u32 {
+        self.opening_spend
+            + self.entry_cost
+            + self
+                .level_charges
+                .iter()
+                .map(|charge| charge.cost)
+                .sum::<u32>()
+    }
+
+    pub fn total_income(&self) -> u32 {
+        self.points_converted + self.milestone_bonus + self.moonrocks_income
+    }
+}
+
+impl GameData {
+    /// Settles the run as if it ended now.
+    pub fn settlement(&self) -> Settlement {
+        let level_charges: Vec<LevelCharge> = self
+            .summary
+            .levels
+            .iter()
+            .filter_map(|level| {
+                let shop = level.shop.as_ref()?;
+                Some(LevelCharge {
+                    level: level.level,
+                    cost: shop.moonrocks_charged,
+                })
+            })
+            .collect();
+        let payout = &self.rules.payout;
+        let stake = match self.practice {
+            true => 0,
+            false => self.stake.multiplier(),
+        };
+
+        let mut settlement = Settlement {
+            opening_spend: self.opening_spend(),
+            entry_cost: self.level_cost(0),
+            level_charges,
+            points_converted: payout.convert(self.points, self.level) * stake,
+            milestone_bonus: payout.milestone_bonus * self.milestones_reached() * stake,
+            moonrocks_income: self.moonrocks_earned * stake,
+            unexplained: 0,
+            net: 0,
+            stake: self.stake,
+            practice: self.practice,
+        };
+        settlement.unexplained = self.moonrocks_spent as i32 - settlement.known_cost() as i32;
+        settlement.net = settlement.total_income() as i32 - settlement.total_cost() as i32;
+        settlement
+    }
+
+    /// Levels completed so far, counting the current one once its milestone
+    /// is met. Level 0, which only unchecked data can hold, has none before it.
+    pub fn milestones_reached(&self) -> u32 {
+        self.level.saturating_sub(1) + u32::from(self.points >= self.milestone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{PayoutPolicy, Rules};
+    use crate::{Action, Game, perform_action_with_rng, step_rng};
+
+    #[test]
+    fn test_settlement_explains_moonrocks_spent() {
+        let Game::Level { game_data } = Game::start(Rules::default(), Stake::X1) else {
+            panic!("start");
+        };
+        let mut game = Game::LevelComplete { game_data };
+        perform_action_with_rng(&mut game, Action::EnterShop, &mut step_rng(1, 0)).unwrap();
+        perform_action_with_rng(&mut game, Action::GoToNextLevel, &mut step_rng(1, 1)).unwrap();
+
+        let Game::Level { mut game_data } = game else {
+            panic!("next level");
+        };
+        game_data.points = 6;
+        game_data.moonrocks_earned = 15;
+
+        let settlement = game_data.settlement();
+        assert_eq!(settlement.opening_spend, 10);
+        assert_eq!(settlement.entry_cost, 10);
+        assert_eq!(
+            settlement.level_charges,
+            vec![LevelCharge { level: 1, cost: 1 }]
+        );
+        assert_eq!(settlement.unexplained, 0);
+        assert_eq!(settlement.total_cost(), game_data.moonrocks_spent);
+        assert_eq!(settlement.net, 6 + 15 - 21);
+    }
+
+    #[test]
+    fn test_costs_match_stored_spend() {
+        // a state built mid-run has no shop records to explain its spend
+        let mut game_data = GameData::builder().level(4).points(3).build();
+        game_data.moonrocks_spent = 23;
+        let settlement = game_data.settlement();
+        assert!(settlement.level_charges.is_empty());
+        assert_eq!(settlement.known_cost(), 20);
+        assert_eq!(settlement.unexplained, 3);
+        assert_eq!(settlement.total_cost(), 23);
+
+        let mut game = Game::start(Rules::default(), Stake::X5);
+        for step in 0.. {
+            let action = match &game {
+                Game::Level { .. } => Action::PullOrb,
+                Game::LevelComplete { game_data } if game_data.level < 3 => Action::EnterShop,
+                Game::Shop { .. } => Action::GoToNextLevel,
+                Game::FiveOrDiePhase { .. } => Action::ConfirmFiveOrDie(false),
+                _ => break,
+            };
+            perform_action_with_rng(&mut game, action, &mut step_rng(7, step)).unwrap();
+        }
+        let game_data = match game {
+            Game::LevelComplete { game_data } | Game::GameOver { game_data, .. } => game_data,
+            _ => unreachable!("loop stops at a completed level or game over"),
+        };
+        assert!(!game_data.settlement().level_charges.is_empty());
+        assert_eq!(game_data.settlement().unexplained, 0);
+        assert_eq!(
+            game_data.settlement().total_cost(),
+            game_data.moonrocks_spent
+        );
+    }
+
+    #[test]
+    fn test_settlement_applies_payout_policy() {
+        let rules = Rules {
+            payout: PayoutPolicy {
+                rate: 200,
+                milestone_bonus: 5,
+                ..PayoutPolicy::default()
+            },
+        };
+        let Game::Level { mut game_data } = Game::start(rules, Stake::X1) else {
+            panic!("start");
+        };
+        game_data.level = 2;
+        game_data.points = game_data.milestone;
+
+        let settlement = game_data.settlement();
+        assert_eq!(settlement.points_converted, game_data.milestone * 2);
+        assert_eq!(settlement.milestone_bonus, 10);
+        assert_eq!(settlement.net, (game_data.milestone * 2 + 10) as i32 - 20);
+    }
+
+    #[test]
+    fn test_level_zero_counts_no_earlier_milestones() {
+        let mut game_data = GameData::new();
+        game_data.level = 0;
+        assert_eq!(game_data.milestones_reached(), 0);
+        game_data.points = game_data.milestone;
+        assert_eq!(game_data.milestones_reached(), 1);
+    }
+
+    #[test]
+    fn test_stake_scales_charges_and_payout() {
+        let mut game = Game::New;
+        perform_action_with_rng(&mut game, Action::StartGame(Stake::X5), &mut step_rng(4, 0))
+            .unwrap();
+        let Game::Level { mut game_data } = game else {
+            panic!("start");
+        };
+        assert_eq!(game_data.moonrocks_spent, 100);
+
+        game_data.points = 3;
+        game_data.moonrocks_earned = 2;
+        let settlement = game_data.settlement();
+        assert_eq!(settlement.opening_spend, 50);
+        assert_eq!(settlement.entry_cost, 50);
+        assert_eq!(settlement.unexplained, 0);
+        assert_eq!(settlement.total_income(), 25);
+        assert_eq!(settlement.net, -75);
+    }
+
+    #[test]
+    fn test_practice_run_settles_to_zero() {
+        let mut game = Game::New;
+        perform_action_with_rng(&mut game, Action::StartPractice, &mut step_rng(4, 0)).unwrap();
+        let Game::Level { mut game_data } = game else {
+            panic!("start");
+        };
+        game_data.points = game_data.milestone;
+        game_data.moonrocks_earned = 15;
+        let mut game = Game::LevelComplete { game_data };
+        perform_action_with_rng(&mut game, Action::EnterShop, &mut step_rng(4, 1)).unwrap();
+        perform_action_with_rng(&mut game, Action::GoToNextLevel, &mut step_rng(4, 2)).unwrap();
+
+        let Game::Level { game_data } = game else {
+            panic!("next level");
+        };
+        assert_eq!(game_data.moonrocks_spent, 0);
+        let settlement = game_data.settlement();
+        assert_eq!(settlement.total_cost(), 0);
+        assert_eq!(settlement.total_income(), 0);
+        assert_eq!(settlement.net, 0);
+    }
+}
@@ -0,0 +1,76 @@
+//! Post-run statistics, recorded into [`GameData`](crate::GameData) as the
+//! game is played and complete once it reaches game over.
+
+use serde::{Deserialize, Serialize};
+
+use crate::settlement::Settlement;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub total_pulls: u32,
+    /// Hp lost to bombs.
+    pub damage_taken: u32,
+    /// Bomb damage absorbed by bomb immunity.
+    pub damage_blocked: u32,
+    /// Most points scored by a single pull.
+    pub best_pull: u32,
+    /// One entry per level reached, in order.
+    pub levels: Vec<LevelSummary>,
+    /// Filled in at game over.
+    pub moonrocks: Settlement,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LevelSummary {
+    pub level: u32,
+    pub pulls: u32,
+    pub chips_earned: u32,
+    /// The shop visited after completing this level, if any.
+    pub shop: Option<ShopSummary>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShopSummary {
+    /// Moonrocks charged for entering the shop.
+    #[serde(default)]
+    pub moonrocks_charged: u32,
+    pub chips_spent: u32,
+    /// Catalogue indices of the orbs bought, in purchase order.
+    pub orbs_bought: Vec<usize>,
+}
+
+impl RunSummary {
+    /// Stats for `level`, starting a new entry when the run moves on to it.
+    pub fn level_mut(&mut self, level: u32) -> &mut LevelSummary {
+        if self.levels.last().is_none_or(|last| last.level != level) {
+            self.levels.push(LevelSummary {
+                level,
+                ..LevelSummary::default()
+            });
+        }
+        self.levels.last_mut().expect("just pushed")
+    }
+
+    pub fn orbs_bought(&self) -> usize {
+        self.levels
+            .iter()
+            .filter_map(|level| level.shop.as_ref())
+            .map(|shop| shop.orbs_bought.len())
+            .sum()
+    }
+
+    pub(crate) fn record_pull(&mut self, level: u32) {
+        self.total_pulls += 1;
+        self.level_mut(level).pulls += 1;
+    }
+
+    pub(crate) fn record_points(&mut self, points: u32) {
+        self.best_pull = self.best_pull.max(points);
+    }
+
+    pub(crate) fn record_purchase(&mut self, level: u32, orb_index: usize, price: u32) {
+        let shop = self.level_mut(level).shop.get_or_insert_default();
+        shop.chips_spent += price;
+        shop.orbs_bought.push(orb_index);
+    }
+}
@@ -0,0 +1,320 @@
+//! Recorded contract execution traces and their replay through
+//! [`perform_action_with_rng`].
+//!
+//! A trace lists, for every step, the action sent, what the contract's
+//! randomness decided during it and the game's storage words read from the
+//! contract after it. The observed outcome is fed back in through
+//! [`Randomness`], so replay checks the model's rules against the contract
+//! whatever RNG the contract uses. Traces must come from real contract
+//! executions; one generated by the model itself would only check the model
+//! against itself.
+//!
+//! Storage words are decoded with [`unpack`], so replay can only pass once the
+//! [`encoding`](crate::encoding) layout matches the contract's.
+
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt;
+
+use crate::encoding::{mismatched_fields, unpack};
+use crate::{
+    Action, ActionError, Game, GameData, GameStateKind, OrbEffect, Randomness,
+    perform_action_with_rng,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub action: Action,
+    #[serde(default)]
+    pub outcome: StepOutcome,
+    /// State the contract reports the game in after the step.
+    pub state: GameStateKind,
+    /// The game's data words as read from contract storage after the step;
+    /// empty while the game is new.
+    #[serde(default)]
+    pub storage: Vec<Felt>,
+}
+
+/// What randomness decided during a step, as observed on the contract.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StepOutcome {
+    /// Orbs pulled, in order: one for a pull, up to five for a FiveOrDie
+    /// phase, including point orbs pulled again after a `PointRewind`.
+    #[serde(default)]
+    pub pulls: Vec<OrbEffect>,
+    /// Catalogue indices stocked on entering the shop, in slot order.
+    #[serde(default)]
+    pub shop: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TraceMismatch {
+    ActionRejected {
+        step: usize,
+        error: ActionError,
+    },
+    /// `fields` is `["state"]` when the state kinds differ and `["storage"]`
+    /// when the recorded words cannot be decoded.
+    StateDiverged {
+        step: usize,
+        fields: Vec<&'static str>,
+        actual: Box<Game>,
+    },
+}
+
+impl Trace {
+    /// Replays the trace from a new game, stopping at the first step whose
+    /// resulting state differs from the recorded one.
+    pub fn replay(&self) -> Result<Game, TraceMismatch> {
+        let mut game = Game::New;
+        for (step, recorded) in self.steps.iter().enumerate() {
+            let mut observed = Observed::new(&recorded.outcome, &game);
+            perform_action_with_rng(&mut game, recorded.action, &mut observed)
+                .map_err(|error| TraceMismatch::ActionRejected { step, error })?;
+
+            let fields = mismatched_recorded_fields(recorded, &game);
+            if !fields.is_empty() {
+                return Err(TraceMismatch::StateDiverged {
+                    step,
+                    fields,
+                    actual: Box::new(game),
+                });
+            }
+        }
+        Ok(game)
+    }
+}
+
+/// Plays back one step's observed outcome. Orbs that are not in the bag and
+/// shop indices that are not candidates are skipped, so a wrong outcome shows
+/// up as a diverged state.
+struct Observed<'a> {
+    /// The pulls that come out of the bag as shuffled.
+    pulls: Vec<OrbEffect>,
+    shop: &'a [usize],
+}
+
+impl<'a> Observed<'a> {
+    fn new(outcome: &'a StepOutcome, game: &Game) -> Self {
+        let pulled = match game {
+            Game::Level { game_data } | Game::FiveOrDiePhase { game_data } => {
+                game_data.pulled_orbs_effects.as_slice()
+            }
+            _ => &[],
+        };
+
+        // a FiveOrDie phase shuffles once, and a point orb put back by
+        // PointRewind is pulled again straight from the top
+        let mut point_pulled = pulled
+            .iter()
+            .any(|effect| matches!(effect, OrbEffect::Point(_)));
+        let mut rewound = false;
+        let mut pulls = Vec::with_capacity(outcome.pulls.len());
+        for effect in &outcome.pulls {
+            if rewound {
+                rewound = false;
+                continue;
+            }
+            pulls.push(*effect);
+            match effect {
+                OrbEffect::Point(_) => point_pulled = true,
+                OrbEffect::PointRewind => rewound = point_pulled,
+                _ => {}
+            }
+        }
+
+        Observed {
+            pulls,
+            shop: &outcome.shop,
+        }
+    }
+}
+
+impl Randomness for Observed<'_> {
+    fn shuffle_bag(&mut self, bag: &mut [OrbEffect], _pulls_so_far: u32) {
+        let mut end = bag.len();
+        for effect in &self.pulls {
+            let Some(index) = bag[..end].iter().rposition(|orb| orb == effect) else {
+                continue;
+            };
+            bag.swap(index, end - 1);
+            end -= 1;
+        }
+    }
+
+    fn pick_shop_orbs(&mut self, candidates: &[usize], amount: usize) -> Vec<usize> {
+        self.shop
+            .iter()
+            .copied()
+            .filter(|index| candidates.contains(index))
+            .take(amount)
+            .collect()
+    }
+}
+
+/// Compares the state kind and the fields the contract stores; model-only
+/// bookkeeping is ignored.
+fn mismatched_recorded_fields(recorded: &TraceStep, game: &Game) -> Vec<&'static str> {
+    if recorded.state != game.kind() {
+        return vec!["state"];
+    }
+    let game_data = match game {
+        Game::New => return vec![],
+        Game::Level { game_data }
+        | Game::LevelComplete { game_data }
+        | Game::FiveOrDiePhase { game_data }
+        | Game::Shop { game_data }
+        | Game::GameOver { game_data, .. } => game_data,
+    };
+    match unpack(&recorded.storage, &game_data.all_orbs) {
+        Ok(stored) => mismatched_data_fields(&stored, game_data),
+        Err(_) => vec!["storage"],
+    }
+}
+
+/// Like [`mismatched_fields`], except that the unpulled orbs may be in any
+/// order: only the pulls are replayed, not the contract's shuffle.
+fn mismatched_data_fields(left: &GameData, right: &GameData) -> Vec<&'static str> {
+    let mut fields = mismatched_fields(left, right);
+    let (left_bag, right_bag) = (&left.pullable_orb_effects, &right.pullable_orb_effects);
+    let count = |bag: &[OrbEffect], effect| bag.iter().filter(|orb| **orb == effect).count();
+    if left_bag.len() == right_bag.len()
+        && left_bag
+            .iter()
+            .all(|effect| count(left_bag, *effect) == count(right_bag, *effect))
+    {
+        fields.retain(|field| *field != "pullable_orb_effects");
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::pack;
+    use crate::{Orb, Stake};
+
+    /// A hand-written trace: start, pull a bomb, then a point orb.
+    fn short_trace() -> Trace {
+        let mut game_data = GameData::new();
+        game_data.moonrocks_spent += GameData::entry_cost(Stake::X1);
+        let started = game_data.clone();
+
+        let bomb = game_data.pullable_orb_effects.remove(0);
+        game_data.pulled_orbs_effects.push(bomb);
+        game_data.hp -= 1;
+        let after_bomb = game_data.clone();
+
+        let point = game_data.pullable_orb_effects.remove(4);
+        game_data.pulled_orbs_effects.push(point);
+        game_data.points += 5;
+
+        Trace {
+            steps: vec![
+                TraceStep {
+                    action: Action::StartGame(Stake::X1),
+                    outcome: StepOutcome::default(),
+                    state: GameStateKind::Level,
+                    storage: pack(&started).unwrap(),
+                },
+                TraceStep {
+                    action: Action::PullOrb,
+                    outcome: StepOutcome {
+                        pulls: vec![OrbEffect::Bomb(1)],
+                        shop: vec![],
+                    },
+                    state: GameStateKind::Level,
+                    storage: pack(&after_bomb).unwrap(),
+                },
+                TraceStep {
+                    action: Action::PullOrb,
+                    outcome: StepOutcome {
+                        pulls: vec![OrbEffect::Point(5)],
+                        shop: vec![],
+                    },
+                    state: GameStateKind::Level,
+                    storage: pack(&game_data).unwrap(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_replay_follows_observed_outcomes() {
+        let trace = short_trace();
+        let Game::Level { game_data } = trace.replay().unwrap() else {
+            panic!("still in the level");
+        };
+        assert_eq!(
+            game_data.pulled_orbs_effects,
+            [OrbEffect::Bomb(1), OrbEffect::Point(5)]
+        );
+    }
+
+    #[test]
+    fn test_replay_detects_divergence() {
+        let mut trace = short_trace();
+        let mut stored = unpack(&trace.steps[2].storage, &Orb::all_orbs()).unwrap();
+        stored.points += 1;
+        trace.steps[2].storage = pack(&stored).unwrap();
+        match trace.replay() {
+            Err(TraceMismatch::StateDiverged { step, fields, .. }) => {
+                assert_eq!(step, 2);
+                assert_eq!(fields, vec!["points"]);
+            }
+            other => panic!("expected divergence, got {other:?}"),
+        }
+
+        // an outcome the rules cannot produce diverges too
+        let mut trace = short_trace();
+        trace.steps[1].outcome.pulls = vec![OrbEffect::Health(1)];
+        assert!(matches!(
+            trace.replay(),
+            Err(TraceMismatch::StateDiverged { step: 1, .. })
+        ));
+
+        let mut trace = short_trace();
+        trace.steps[1].state = GameStateKind::FiveOrDiePhase;
+        trace.steps[2].storage.truncate(1);
+        let fields_at = |trace: &Trace, step| match trace.replay() {
+            Err(TraceMismatch::StateDiverged {
+                step: at, fields, ..
+            }) if at == step => fields,
+            other => panic!("expected divergence at {step}, got {other:?}"),
+        };
+        assert_eq!(fields_at(&trace, 1), vec!["state"]);
+        trace.steps[1].state = GameStateKind::Level;
+        assert_eq!(fields_at(&trace, 2), vec!["storage"]);
+    }
+
+    #[test]
+    fn test_rewound_pull_is_not_placed_in_the_bag() {
+        let outcome = StepOutcome {
+            pulls: vec![
+                OrbEffect::PointRewind,
+                OrbEffect::Point(5),
+                OrbEffect::PointRewind,
+                OrbEffect::Point(5),
+                OrbEffect::Bomb(1),
+            ],
+            shop: vec![],
+        };
+        let game = Game::FiveOrDiePhase {
+            game_data: GameData::new(),
+        };
+        // the first rewind finds no point orb to put back, the second does
+        assert_eq!(
+            Observed::new(&outcome, &game).pulls,
+            [
+                OrbEffect::PointRewind,
+                OrbEffect::Point(5),
+                OrbEffect::PointRewind,
+                OrbEffect::Bomb(1),
+            ]
+        );
+    }
+}
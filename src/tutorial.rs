@@ -0,0 +1,157 @@
+//! Scripted tutorial run.
+//!
+//! The tutorial is a practice run with a chip orb and a FiveOrDie orb added to
+//! the bag, whose first level and first shop play out from a [`Script`]. Each
+//! action reports the [`Prompt`]s the UI should show, once per run.
+
+use serde::{Deserialize, Serialize};
+
+use crate::rules::Rules;
+use crate::script::Script;
+use crate::{Action, ActionError, Game, GameData, Orb, OrbEffect, perform_action_with_rng};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Prompt {
+    FirstBomb,
+    FirstMultiplier,
+    FirstFiveOrDie,
+    FirstShop,
+}
+
+/// Catalogue indices of the orbs the tutorial adds to the starting bag.
+const GLITCH_CHIPS_ORB: usize = 5;
+const FIVE_OR_DIE_ORB: usize = 6;
+
+/// Level 1 teaches chips, the multiplier and a bomb, then offers FiveOrDie;
+/// either choice completes the level on the next point orb.
+const PULLS: [OrbEffect; 7] = [
+    OrbEffect::GlitchChips(15),
+    OrbEffect::Point(5),
+    OrbEffect::Multiplier(100),
+    OrbEffect::Bomb(1),
+    OrbEffect::FiveOrDie,
+    OrbEffect::Point(5),
+    OrbEffect::Point(5),
+];
+
+/// The first shop: an affordable point orb up front.
+const SHOP_ORBS: [usize; 6] = [4, 5, 12, 15, 13, 18];
+
+#[derive(Clone, Debug)]
+pub struct Tutorial {
+    game: Game,
+    script: Script,
+    shown: Vec<Prompt>,
+}
+
+impl Default for Tutorial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tutorial {
+    pub fn new() -> Self {
+        let mut catalogue = Orb::all_orbs();
+        catalogue[GLITCH_CHIPS_ORB].count = 1;
+        catalogue[FIVE_OR_DIE_ORB].count = 1;
+
+        let mut game = Game::start_practice(Rules::default());
+        if let Game::Level { game_data } = &mut game {
+            game_data.set_catalogue(catalogue);
+        }
+        Tutorial {
+            game,
+            script: Script::new(PULLS.to_vec()).with_shop_orbs(SHOP_ORBS.to_vec()),
+            shown: Vec::new(),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Performs `action` and returns the prompts it triggered for the first
+    /// time.
+    pub fn perform_action(&mut self, action: Action) -> Result<Vec<Prompt>, ActionError> {
+        perform_action_with_rng(&mut self.game, action, &mut self.script)?;
+
+        let prompts: Vec<Prompt> = prompts(&self.game)
+            .into_iter()
+            .filter(|prompt| !self.shown.contains(prompt))
+            .collect();
+        self.shown.extend(&prompts);
+        Ok(prompts)
+    }
+}
+
+/// Prompts that apply to the state `game` is in.
+fn prompts(game: &Game) -> Vec<Prompt> {
+    let game_data = match game {
+        Game::New => return Vec::new(),
+        Game::Level { game_data }
+        | Game::LevelComplete { game_data }
+        | Game::FiveOrDiePhase { game_data }
+        | Game::Shop { game_data }
+        | Game::GameOver { game_data, .. } => game_data,
+    };
+
+    let mut prompts = Vec::new();
+    if pulled(game_data, |effect| matches!(effect, OrbEffect::Bomb(_))) {
+        prompts.push(Prompt::FirstBomb);
+    }
+    if pulled(game_data, |effect| {
+        matches!(effect, OrbEffect::Multiplier(_))
+    }) {
+        prompts.push(Prompt::FirstMultiplier);
+    }
+    match game {
+        Game::FiveOrDiePhase { .. } => prompts.push(Prompt::FirstFiveOrDie),
+        Game::Shop { .. } => prompts.push(Prompt::FirstShop),
+        _ => {}
+    }
+    prompts
+}
+
+fn pulled(game_data: &GameData, effect: impl Fn(&OrbEffect) -> bool) -> bool {
+    game_data.pulled_orbs_effects.iter().any(effect)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tutorial_prompts_follow_script() {
+        let mut tutorial = Tutorial::new();
+        let mut prompts = Vec::new();
+        for _ in 0..5 {
+            prompts.push(tutorial.perform_action(Action::PullOrb).unwrap());
+        }
+        assert_eq!(
+            prompts,
+            vec![
+                vec![],
+                vec![],
+                vec![Prompt::FirstMultiplier],
+                vec![Prompt::FirstBomb],
+                vec![Prompt::FirstFiveOrDie],
+            ]
+        );
+
+        let prompts = tutorial
+            .perform_action(Action::ConfirmFiveOrDie(true))
+            .unwrap();
+        assert!(prompts.is_empty());
+        assert!(matches!(tutorial.game(), Game::LevelComplete { .. }));
+
+        let prompts = tutorial.perform_action(Action::EnterShop).unwrap();
+        assert_eq!(prompts, vec![Prompt::FirstShop]);
+        let Game::Shop { game_data } = tutorial.game() else {
+            panic!("shop");
+        };
+        assert_eq!(game_data.sale_orbs_indices, SHOP_ORBS);
+        assert_eq!(game_data.glitch_chips, 15);
+        assert_eq!(game_data.moonrocks_spent, 0);
+    }
+}
@@ -0,0 +1,296 @@
+//! Server-side verification of client-submitted action logs.
+//!
+//! A run is re-executed from a new game and the claimed payout is checked
+//! against the settlement the state machine computed, so rewards can be paid
+//! without trusting the client. [`verify_payout`] takes the run's randomness
+//! per step; [`verify_run`] and [`verify_committed_run`] wrap it for the two
+//! ways a run is seeded. Runs are replayed under the rules they were started
+//! with, which the verifier has to be given.
+
+use serde::Serialize;
+use starknet_types_core::felt::Felt;
+
+use crate::commit_reveal::{CommittedStep, Reveal, action_rng, seed_commitment};
+use crate::rules::Rules;
+use crate::{Action, ActionError, Game, Randomness, perform_action_with_rules, step_rng};
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct VerifiedRun {
+    pub game: Game,
+    pub moonrocks_diff: i32,
+    pub steps: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub enum VerificationError {
+    /// The revealed seed and salt do not open the commitment.
+    SeedMismatch,
+    /// The action at `step` is not legal in the state the run reached.
+    IllegalAction {
+        step: usize,
+        action: Action,
+        error: ActionError,
+    },
+    RunNotOver,
+    ClaimMismatch {
+        claimed: i32,
+        actual: i32,
+    },
+}
+
+/// Re-executes `actions` under `rules`, the `i`-th drawing from
+/// `rng_for(i)`, and checks the run ended with the claimed payout.
+pub fn verify_payout<R: Randomness>(
+    actions: &[Action],
+    rules: &Rules,
+    mut rng_for: impl FnMut(usize) -> R,
+    claimed_moonrocks_diff: i32,
+) -> Result<VerifiedRun, VerificationError> {
+    let mut game = Game::New;
+    for (step, action) in actions.iter().enumerate() {
+        perform_action_with_rules(&mut game, *action, rules, &mut rng_for(step)).map_err(
+            |error| VerificationError::IllegalAction {
+                step,
+                action: *action,
+                error,
+            },
+        )?;
+    }
+
+    let Game::GameOver { moonrocks_diff, .. } = game else {
+        return Err(VerificationError::RunNotOver);
+    };
+    if moonrocks_diff != claimed_moonrocks_diff {
+        return Err(VerificationError::ClaimMismatch {
+            claimed: claimed_moonrocks_diff,
+            actual: moonrocks_diff,
+        });
+    }
+
+    Ok(VerifiedRun {
+        game,
+        moonrocks_diff,
+        steps: actions.len(),
+    })
+}
+
+/// A [`GameSession`](crate::session::GameSession) log played under the
+/// default rules: the `i`-th action draws from [`step_rng`]`(seed, i)`.
+pub fn verify_run(
+    seed: u64,
+    actions: &[Action],
+    claimed_moonrocks_diff: i32,
+) -> Result<VerifiedRun, VerificationError> {
+    verify_run_with_rules(seed, actions, &Rules::default(), claimed_moonrocks_diff)
+}
+
+/// Same as [`verify_run`] for a session started under `rules`.
+pub fn verify_run_with_rules(
+    seed: u64,
+    actions: &[Action],
+    rules: &Rules,
+    claimed_moonrocks_diff: i32,
+) -> Result<VerifiedRun, VerificationError> {
+    verify_payout(
+        actions,
+        rules,
+        |step| step_rng(seed, step as u64),
+        claimed_moonrocks_diff,
+    )
+}
+
+/// A [`CommittedRun`](crate::commit_reveal::CommittedRun) log: `reveal` must
+/// open `commitment`, and the `i`-th action draws from
+/// [`action_rng`]`(seed, client_nonce, i)`. `rules` are the ones the run was
+/// started under.
+pub fn verify_committed_run(
+    commitment: Felt,
+    reveal: &Reveal,
+    steps: &[CommittedStep],
+    rules: &Rules,
+    claimed_moonrocks_diff: i32,
+) -> Result<VerifiedRun, VerificationError> {
+    if seed_commitment(reveal) != commitment {
+        return Err(VerificationError::SeedMismatch);
+    }
+    let actions: Vec<Action> = steps.iter().map(|step| step.action).collect();
+    verify_payout(
+        &actions,
+        rules,
+        |step| action_rng(&reveal.seed, &steps[step].client_nonce, step as u64),
+        claimed_moonrocks_diff,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit_reveal::CommittedRun;
+    use crate::rtp::{Strategy, ThresholdStrategy};
+    use crate::rules::PayoutPolicy;
+    use crate::session::GameSession;
+    use crate::{ActionKind, GameStateKind, perform_action_with_rng};
+
+    fn play(seed: u64) -> (Vec<Action>, i32) {
+        let mut strategy = ThresholdStrategy::FIRST_LEVEL;
+        let mut game = Game::New;
+        let mut actions = Vec::new();
+        while !matches!(game, Game::GameOver { .. }) {
+            let action = strategy.choose(&game);
+            let step = actions.len() as u64;
+            perform_action_with_rng(&mut game, action, &mut step_rng(seed, step)).unwrap();
+            actions.push(action);
+        }
+        let Game::GameOver { moonrocks_diff, .. } = game else {
+            unreachable!("loop runs until game over");
+        };
+        (actions, moonrocks_diff)
+    }
+
+    #[test]
+    fn test_verify_session_under_its_rules() {
+        let mut session = GameSession::new(1, "alice", 11);
+        session.rules = Rules {
+            payout: PayoutPolicy {
+                rate: 300,
+                ..PayoutPolicy::default()
+            },
+        };
+        let mut strategy = ThresholdStrategy::FIRST_LEVEL;
+        while !matches!(session.game(), Game::GameOver { .. }) {
+            session
+                .perform_action(strategy.choose(session.game()))
+                .unwrap();
+        }
+        let Game::GameOver { moonrocks_diff, .. } = *session.game() else {
+            unreachable!("loop runs until game over");
+        };
+
+        let verified =
+            verify_run_with_rules(11, session.actions(), &session.rules, moonrocks_diff).unwrap();
+        assert_eq!(&verified.game, session.game());
+        // replayed under the default rules, the same log pays less
+        assert!(matches!(
+            verify_run(11, session.actions(), moonrocks_diff),
+            Err(VerificationError::ClaimMismatch { claimed, actual }) if actual < claimed
+        ));
+    }
+
+    #[test]
+    fn test_verify_honest_run() {
+        let (actions, moonrocks_diff) = play(11);
+        let verified = verify_run(11, &actions, moonrocks_diff).unwrap();
+        assert_eq!(verified.moonrocks_diff, moonrocks_diff);
+        assert_eq!(verified.steps, actions.len());
+    }
+
+    #[test]
+    fn test_verify_rejects_bad_claims() {
+        let (mut actions, moonrocks_diff) = play(11);
+        assert_eq!(
+            verify_run(11, &actions, moonrocks_diff + 1),
+            Err(VerificationError::ClaimMismatch {
+                claimed: moonrocks_diff + 1,
+                actual: moonrocks_diff,
+            })
+        );
+
+        assert_eq!(
+            verify_run(11, &actions[..1], moonrocks_diff),
+            Err(VerificationError::RunNotOver)
+        );
+
+        actions.insert(1, Action::EnterShop);
+        assert_eq!(
+            verify_run(11, &actions, moonrocks_diff),
+            Err(VerificationError::IllegalAction {
+                step: 1,
+                action: Action::EnterShop,
+                error: ActionError::InvalidAction {
+                    state: GameStateKind::Level,
+                    action: ActionKind::EnterShop,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_committed_run() {
+        let mut run = CommittedRun::new();
+        assert_eq!(run.reveal(), None);
+        let mut strategy = ThresholdStrategy::FIRST_LEVEL;
+        let mut nonce = 1u64;
+        while !matches!(run.game(), Game::GameOver { .. }) {
+            run.perform(strategy.choose(run.game()), Felt::from(nonce))
+                .unwrap();
+            nonce += 1;
+        }
+        let Game::GameOver { moonrocks_diff, .. } = *run.game() else {
+            unreachable!("loop runs until game over");
+        };
+        let reveal = run.reveal().unwrap();
+
+        let verified = verify_committed_run(
+            run.commitment(),
+            &reveal,
+            run.steps(),
+            run.rules(),
+            moonrocks_diff,
+        )
+        .unwrap();
+        assert_eq!(&verified.game, run.game());
+        assert_eq!(
+            verify_committed_run(
+                run.commitment(),
+                &reveal,
+                run.steps(),
+                run.rules(),
+                moonrocks_diff + 1
+            ),
+            Err(VerificationError::ClaimMismatch {
+                claimed: moonrocks_diff + 1,
+                actual: moonrocks_diff,
+            })
+        );
+
+        let forged = Reveal {
+            seed: reveal.seed + Felt::ONE,
+            ..reveal
+        };
+        assert_eq!(
+            verify_committed_run(
+                run.commitment(),
+                &forged,
+                run.steps(),
+                run.rules(),
+                moonrocks_diff
+            ),
+            Err(VerificationError::SeedMismatch)
+        );
+        // the seed alone, or with another salt, does not open the commitment
+        let other_salt = Reveal {
+            salt: reveal.salt + Felt::ONE,
+            ..reveal
+        };
+        assert_eq!(
+            verify_committed_run(
+                run.commitment(),
+                &other_salt,
+                run.steps(),
+                run.rules(),
+                moonrocks_diff
+            ),
+            Err(VerificationError::SeedMismatch)
+        );
+        assert_eq!(
+            verify_committed_run(
+                run.commitment(),
+                &reveal,
+                &run.steps()[..1],
+                run.rules(),
+                moonrocks_diff
+            ),
+            Err(VerificationError::RunNotOver)
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Moonrock balances that persist across games.
+//!
+//! The state machine only records what a game spent and reports a signed
+//! `moonrocks_diff` at game over. [`Player`] settles that against a
+//! [`Wallet`]: level costs are debited before the action that charges them,
+//! and the payout is credited when the game ends.
+//!
+//! A run also counts [`GameData::OPENING_MOONROCKS_SPENT`] as spent on top of
+//! the entry cost. Until the contract confirms it charges both, the wallet
+//! neither debits that opening spend nor takes it out of the payout.
+
+use serde::{Deserialize, Serialize};
+
+use crate::rules::Rules;
+use crate::{Action, ActionError, Game, GameData, Randomness, perform_action_with_rules};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Wallet {
+    moonrocks: u32,
+}
+
+impl Wallet {
+    pub fn new(moonrocks: u32) -> Self {
+        Wallet { moonrocks }
+    }
+
+    pub fn balance(&self) -> u32 {
+        self.moonrocks
+    }
+
+    pub fn credit(&mut self, moonrocks: u32) {
+        self.moonrocks += moonrocks;
+    }
+
+    pub fn debit(&mut self, moonrocks: u32) -> Result<(), ActionError> {
+        self.moonrocks =
+            self.moonrocks
+                .checked_sub(moonrocks)
+                .ok_or(ActionError::InsufficientMoonrocks {
+                    cost: moonrocks,
+                    balance: self.moonrocks,
+                })?;
+        Ok(())
+    }
+}
+
+/// Moonrocks `action` charges when performed on `game`.
+pub fn action_cost(game: &Game, action: &Action) -> u32 {
+    match (game, action) {
+        (Game::New | Game::GameOver { .. }, Action::StartGame(stake)) => {
+            GameData::entry_cost(*stake)
+        }
+        (Game::LevelComplete { game_data }, Action::EnterShop)
+            if (game_data.level as usize) < GameData::LEVEL_COST_IN_MOONROCKS.len() =>
+        {
+            game_data.level_cost(game_data.level)
+        }
+        _ => 0,
+    }
+}
+
+/// A player's wallet and current game.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Player {
+    pub wallet: Wallet,
+    /// Rules the player's games are started under.
+    #[serde(default)]
+    pub rules: Rules,
+    game: Game,
+    /// Moonrocks debited for the current game, returned with the payout.
+    staked: u32,
+}
+
+impl Player {
+    pub fn new(wallet: Wallet) -> Self {
+        Player {
+            wallet,
+            rules: Rules::default(),
+            game: Game::New,
+            staked: 0,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn perform_action(&mut self, action: Action) -> Result<(), ActionError> {
+        self.perform_action_with_rng(action, &mut rand::rng())
+    }
+
+    /// Performs `action` if the wallet covers its cost. Starting a game after
+    /// a game over starts a fresh one.
+    pub fn perform_action_with_rng<R: Randomness + ?Sized>(
+        &mut self,
+        action: Action,
+        rng: &mut R,
+    ) -> Result<(), ActionError> {
+        let cost = action_cost(&self.game, &action);
+        if cost > self.wallet.balance() {
+            return Err(ActionError::InsufficientMoonrocks {
+                cost,
+                balance: self.wallet.balance(),
+            });
+        }
+
+        if let (Game::GameOver { .. }, Action::StartGame(_) | Action::StartPractice) =
+            (&self.game, action)
+        {
+            self.game = Game::New;
+        }
+        perform_action_with_rules(&mut self.game, action, &self.rules, rng)?;
+        self.wallet.debit(cost)?;
+        self.staked += cost;
+
+        if let Game::GameOver {
+            ref game_data,
+            moonrocks_diff,
+            ..
+        } = self.game
+        {
+            let staked = self.staked + game_data.opening_spend();
+            let payout = (moonrocks_diff + staked as i32).max(0) as u32;
+            self.wallet.credit(payout);
+            self.staked = 0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtp::{Strategy, ThresholdStrategy};
+    use crate::{Stake, step_rng};
+
+    #[test]
+    fn test_entry_requires_moonrocks() {
+        let mut player = Player::new(Wallet::new(9));
+        assert_eq!(
+            player.perform_action(Action::StartGame(Stake::X1)),
+            Err(ActionError::InsufficientMoonrocks {
+                cost: 10,
+                balance: 9
+            })
+        );
+        assert_eq!(player.game(), &Game::New);
+        assert_eq!(player.wallet.balance(), 9);
+    }
+
+    #[test]
+    fn test_wallet_settles_game() {
+        let mut player = Player::new(Wallet::new(25));
+        player.perform_action(Action::StartGame(Stake::X1)).unwrap();
+        assert_eq!(player.wallet.balance(), 15);
+
+        let mut strategy = ThresholdStrategy::FIRST_LEVEL;
+        let mut step = 0;
+        while !matches!(player.game(), Game::GameOver { .. }) {
+            let action = strategy.choose(player.game());
+            player
+                .perform_action_with_rng(action, &mut step_rng(5, step))
+                .unwrap();
+            step += 1;
+        }
+
+        let Game::GameOver { moonrocks_diff, .. } = *player.game() else {
+            unreachable!()
+        };
+        // the opening spend counted in moonrocks_diff is not charged
+        let opening = GameData::OPENING_MOONROCKS_SPENT as i32;
+        assert_eq!(
+            player.wallet.balance() as i32,
+            25 - 10 + (moonrocks_diff + opening + 10).max(0)
+        );
+
+        // the next game starts from the settled balance
+        player.perform_action(Action::StartGame(Stake::X1)).unwrap();
+        assert!(matches!(player.game(), Game::Level { .. }));
+    }
+}
@@ -0,0 +1,30 @@
+//! Replays every trace recorded from contract executions in `tests/traces`
+//! and checks the model reproduces the recorded state after each step. See
+//! `tests/traces/README.md` for how traces are recorded.
+
+use std::fs;
+use std::path::Path;
+
+use statemachine_glitchbomb::trace::Trace;
+
+#[test]
+#[ignore = "no recorded traces yet"]
+fn test_recorded_traces_match_model() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/traces");
+    let mut replayed = 0;
+
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+
+        let trace: Trace = serde_json::from_str(&fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+        if let Err(mismatch) = trace.replay() {
+            panic!("{}: {mismatch:?}", path.display());
+        }
+        replayed += 1;
+    }
+    assert!(replayed > 0, "no traces found in {}", dir.display());
+}
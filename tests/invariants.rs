@@ -0,0 +1,129 @@
+//! Property tests: random action sequences, from a new game and from built
+//! mid-run states, must never panic and must keep the game data consistent.
+//! Arithmetic underflow (chips, hp) panics in test builds, so "no panic" also
+//! covers it.
+
+use proptest::prelude::*;
+use statemachine_glitchbomb::{
+    Action, Game, GameData, InShopSlot, MAX_LEVEL, Stake, perform_action_with_rng, step_rng,
+};
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        4 => Just(Action::PullOrb),
+        1 => prop::sample::select(Stake::ALL.to_vec()).prop_map(Action::StartGame),
+        1 => Just(Action::StartPractice),
+        1 => Just(Action::CashOut),
+        2 => Just(Action::EnterShop),
+        2 => prop::sample::select(InShopSlot::ALL.to_vec()).prop_map(Action::BuyOrb),
+        1 => any::<bool>().prop_map(Action::ConfirmFiveOrDie),
+        2 => Just(Action::GoToNextLevel),
+    ]
+}
+
+/// Where in a level a built mid-run state is.
+#[derive(Clone, Copy, Debug)]
+enum Phase {
+    Level,
+    LevelComplete,
+    FiveOrDie,
+    Shop,
+}
+
+/// Any state of a run past its start, anywhere in the run, with a random bag
+/// and, in shops, a random (possibly short) stock.
+fn mid_run() -> impl Strategy<Value = Game> {
+    (
+        1..=MAX_LEVEL,
+        1..=5u32,
+        0..100u32,
+        prop::collection::vec(0..4u32, 21),
+        prop::sample::select(vec![
+            Phase::Level,
+            Phase::LevelComplete,
+            Phase::FiveOrDie,
+            Phase::Shop,
+        ]),
+        prop::collection::vec(0..21usize, 0..=6),
+    )
+        .prop_map(|(level, hp, glitch_chips, counts, phase, sale_orbs)| {
+            let mut builder = GameData::builder()
+                .level(level)
+                .hp(hp)
+                .glitch_chips(glitch_chips);
+            for (index, count) in counts.into_iter().enumerate() {
+                builder = builder.orb_count(index, count);
+            }
+            let mut game_data = builder.build();
+            match phase {
+                Phase::Level => Game::Level { game_data },
+                Phase::LevelComplete => {
+                    game_data.points = game_data.milestone;
+                    Game::LevelComplete { game_data }
+                }
+                Phase::FiveOrDie => Game::FiveOrDiePhase { game_data },
+                Phase::Shop => {
+                    game_data.points = game_data.milestone;
+                    game_data.sale_orbs_indices = sale_orbs;
+                    Game::Shop { game_data }
+                }
+            }
+        })
+}
+
+fn check_invariants(game: &Game) -> Result<(), TestCaseError> {
+    let game_data = match game {
+        Game::New => return Ok(()),
+        Game::Level { game_data }
+        | Game::LevelComplete { game_data }
+        | Game::FiveOrDiePhase { game_data }
+        | Game::Shop { game_data }
+        | Game::GameOver { game_data, .. } => game_data,
+    };
+
+    prop_assert!(game_data.hp <= game_data.max_hp);
+    prop_assert!((1..=MAX_LEVEL).contains(&game_data.level));
+
+    // purchases only reach the bag at the next level, and a failed FiveOrDie
+    // ends the game with its FiveOrDie orbs still set aside
+    if let Game::Level { .. } | Game::LevelComplete { .. } | Game::FiveOrDiePhase { .. } = game {
+        for orb in &game_data.all_orbs {
+            let in_bag = game_data
+                .pullable_orb_effects
+                .iter()
+                .chain(&game_data.pulled_orbs_effects)
+                .filter(|effect| **effect == orb.effect)
+                .count();
+            prop_assert_eq!(in_bag, orb.count as usize, "{:?}", orb.effect);
+        }
+    }
+    Ok(())
+}
+
+fn play(mut game: Game, seed: u64, actions: &[Action]) -> Result<(), TestCaseError> {
+    check_invariants(&game)?;
+    for (step, action) in actions.iter().enumerate() {
+        let _ = perform_action_with_rng(&mut game, *action, &mut step_rng(seed, step as u64));
+        check_invariants(&game)?;
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_random_runs_keep_invariants(
+        seed in any::<u64>(),
+        actions in prop::collection::vec(action(), 1..300),
+    ) {
+        play(Game::New, seed, &actions)?;
+    }
+
+    #[test]
+    fn test_mid_run_states_keep_invariants(
+        game in mid_run(),
+        seed in any::<u64>(),
+        actions in prop::collection::vec(action(), 1..100),
+    ) {
+        play(game, seed, &actions)?;
+    }
+}